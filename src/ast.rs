@@ -1,42 +1,98 @@
-pub use self::{language::*, primitives::*};
+pub use self::{language::*, pretty::*, primitives::*};
 use std::fmt;
 
 mod language;
+mod pretty;
 mod primitives;
+#[cfg(test)]
+pub mod strategy;
 
+/// A value printed using `Debug`.
+///
+/// Printing a tree with `Display` (or [`Pretty`]) and parsing it back yields the same tree, as long
+/// as the parser could have produced it in the first place: an empty `ItemMap` prints as `{}`, for
+/// example, which reads back as an `ItemEmpty`.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
 pub enum AstItem<'a> {
+    ItemEmpty(ItemEmpty),
     ItemList(ItemList<'a>),
     ItemMap(ItemMap<'a>),
     ItemOption(ItemOption<'a>),
+    ItemRange(ItemRange<'a>),
     ItemSet(ItemSet<'a>),
     ItemStruct(ItemStruct<'a>),
     ItemTuple(ItemTuple<'a>),
     ItemTupleStruct(ItemTupleStruct<'a>),
     ItemUnitStruct(ItemUnitStruct<'a>),
     ValueBool(ValueBool),
+    ValueBytes(ValueBytes<'a>),
+    ValueDuration(ValueDuration<'a>),
+    ValueIpAddr(ValueIpAddr<'a>),
     ValueNum(ValueNum<'a>),
+    ValueOpaque(ValueOpaque<'a>),
+    ValueSocketAddr(ValueSocketAddr<'a>),
     ValueStr(ValueStr<'a>),
 }
 
+impl<'a> AstItem<'a> {
+    /// Copy the borrowed text, so that the tree no longer borrows the input and can be stored or
+    /// sent to another thread.
+    pub fn into_owned(self) -> AstItem<'static> {
+        match self {
+            AstItem::ItemEmpty(x) => x.into(),
+            AstItem::ItemList(x) => x.into_owned().into(),
+            AstItem::ItemMap(x) => x.into_owned().into(),
+            AstItem::ItemOption(x) => x.into_owned().into(),
+            AstItem::ItemRange(x) => x.into_owned().into(),
+            AstItem::ItemSet(x) => x.into_owned().into(),
+            AstItem::ItemStruct(x) => x.into_owned().into(),
+            AstItem::ItemTuple(x) => x.into_owned().into(),
+            AstItem::ItemTupleStruct(x) => x.into_owned().into(),
+            AstItem::ItemUnitStruct(x) => x.into_owned().into(),
+            AstItem::ValueBool(x) => x.into(),
+            AstItem::ValueBytes(x) => x.into_owned().into(),
+            AstItem::ValueDuration(x) => x.into_owned().into(),
+            AstItem::ValueIpAddr(x) => x.into_owned().into(),
+            AstItem::ValueNum(x) => x.into_owned().into(),
+            AstItem::ValueOpaque(x) => x.into_owned().into(),
+            AstItem::ValueSocketAddr(x) => x.into_owned().into(),
+            AstItem::ValueStr(x) => x.into_owned().into(),
+        }
+    }
+}
+
 impl<'a> fmt::Display for AstItem<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            AstItem::ItemEmpty(x) => x.fmt(f),
             AstItem::ItemList(x) => x.fmt(f),
             AstItem::ItemMap(x) => x.fmt(f),
             AstItem::ItemOption(x) => x.fmt(f),
+            AstItem::ItemRange(x) => x.fmt(f),
             AstItem::ItemSet(x) => x.fmt(f),
             AstItem::ItemStruct(x) => x.fmt(f),
             AstItem::ItemTuple(x) => x.fmt(f),
             AstItem::ItemTupleStruct(x) => x.fmt(f),
             AstItem::ItemUnitStruct(x) => x.fmt(f),
             AstItem::ValueBool(x) => x.fmt(f),
+            AstItem::ValueBytes(x) => x.fmt(f),
+            AstItem::ValueDuration(x) => x.fmt(f),
+            AstItem::ValueIpAddr(x) => x.fmt(f),
             AstItem::ValueNum(x) => x.fmt(f),
+            AstItem::ValueOpaque(x) => x.fmt(f),
+            AstItem::ValueSocketAddr(x) => x.fmt(f),
             AstItem::ValueStr(x) => x.fmt(f),
         }
     }
 }
 
+impl<'a> From<ItemEmpty> for AstItem<'a> {
+    fn from(value: ItemEmpty) -> Self {
+        Self::ItemEmpty(value)
+    }
+}
+
 impl<'a> From<ItemList<'a>> for AstItem<'a> {
     fn from(value: ItemList<'a>) -> Self {
         Self::ItemList(value)
@@ -55,6 +111,12 @@ impl<'a> From<ItemOption<'a>> for AstItem<'a> {
     }
 }
 
+impl<'a> From<ItemRange<'a>> for AstItem<'a> {
+    fn from(value: ItemRange<'a>) -> Self {
+        Self::ItemRange(value)
+    }
+}
+
 impl<'a> From<ItemSet<'a>> for AstItem<'a> {
     fn from(value: ItemSet<'a>) -> Self {
         Self::ItemSet(value)
@@ -91,12 +153,42 @@ impl<'a> From<ValueBool> for AstItem<'a> {
     }
 }
 
+impl<'a> From<ValueBytes<'a>> for AstItem<'a> {
+    fn from(value: ValueBytes<'a>) -> Self {
+        Self::ValueBytes(value)
+    }
+}
+
+impl<'a> From<ValueDuration<'a>> for AstItem<'a> {
+    fn from(value: ValueDuration<'a>) -> Self {
+        Self::ValueDuration(value)
+    }
+}
+
+impl<'a> From<ValueIpAddr<'a>> for AstItem<'a> {
+    fn from(value: ValueIpAddr<'a>) -> Self {
+        Self::ValueIpAddr(value)
+    }
+}
+
 impl<'a> From<ValueNum<'a>> for AstItem<'a> {
     fn from(value: ValueNum<'a>) -> Self {
         Self::ValueNum(value)
     }
 }
 
+impl<'a> From<ValueOpaque<'a>> for AstItem<'a> {
+    fn from(value: ValueOpaque<'a>) -> Self {
+        Self::ValueOpaque(value)
+    }
+}
+
+impl<'a> From<ValueSocketAddr<'a>> for AstItem<'a> {
+    fn from(value: ValueSocketAddr<'a>) -> Self {
+        Self::ValueSocketAddr(value)
+    }
+}
+
 impl<'a> From<ValueStr<'a>> for AstItem<'a> {
     fn from(value: ValueStr<'a>) -> Self {
         Self::ValueStr(value)
@@ -1,107 +1,364 @@
 use super::AstItem;
 use crate::util::DebugToDisplay;
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
     collections::{BTreeMap, BTreeSet},
     fmt,
 };
 
+/// An empty pair of braces (`{}`).
+///
+/// Empty maps and empty sets are formatted identically, so the parser cannot tell which one it is
+/// looking at.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ItemEmpty;
+
+impl fmt::Display for ItemEmpty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("{}")
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ItemList<'a>(pub Vec<AstItem<'a>>);
 
-impl<'a> fmt::Display for ItemList<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl<'a> ItemList<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemList<'static> {
+        ItemList(self.0.into_iter().map(AstItem::into_owned).collect())
+    }
+}
+
+impl<'a> ItemList<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
         f.debug_list()
-            .entries(self.0.iter().map(DebugToDisplay))
+            .entries(self.0.iter().map(|x| DebugToDisplay(Flat(x, sort))))
             .finish()
     }
 }
 
+impl<'a> fmt::Display for ItemList<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+/// A map.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ItemMap<'a>(pub BTreeMap<AstItem<'a>, AstItem<'a>>);
 
-impl<'a> fmt::Display for ItemMap<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl<'a> ItemMap<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemMap<'static> {
+        ItemMap(
+            self.0
+                .into_iter()
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect(),
+        )
+    }
+}
+
+impl<'a> ItemMap<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
+        let mut entries = self.0.iter().collect::<Vec<_>>();
+        if sort {
+            entries.sort_by(|(lhs, _), (rhs, _)| natural_cmp(lhs, rhs));
+        }
+
         f.debug_map()
             .entries(
-                self.0
-                    .iter()
-                    .map(|(k, v)| (DebugToDisplay(k), DebugToDisplay(v))),
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (DebugToDisplay(Flat(k, sort)), DebugToDisplay(Flat(v, sort)))),
             )
             .finish()
     }
 }
 
+impl<'a> fmt::Display for ItemMap<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ItemOption<'a>(pub Option<Box<AstItem<'a>>>);
 
+impl<'a> ItemOption<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemOption<'static> {
+        ItemOption(self.0.map(|x| Box::new(x.into_owned())))
+    }
+}
+
+impl<'a> ItemOption<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
+        match self.0.as_deref() {
+            Some(x) => f
+                .debug_tuple("Some")
+                .field(&DebugToDisplay(Flat(x, sort)))
+                .finish(),
+            None => f.write_str("None"),
+        }
+    }
+}
+
 impl<'a> fmt::Display for ItemOption<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.0.as_deref() {
-            Some(x) => f.debug_tuple("Some").field(&DebugToDisplay(x)).finish(),
-            None => f.debug_struct("None").finish(),
+        self.write(f, false)
+    }
+}
+
+/// A range (`a..b`, `a..=b`, `a..`, `..b` or `..`).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ItemRange<'a>(
+    pub Option<Box<AstItem<'a>>>,
+    pub RangeLimits,
+    pub Option<Box<AstItem<'a>>>,
+);
+
+impl<'a> ItemRange<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemRange<'static> {
+        ItemRange(
+            self.0.map(|x| Box::new(x.into_owned())),
+            self.1,
+            self.2.map(|x| Box::new(x.into_owned())),
+        )
+    }
+}
+
+impl<'a> ItemRange<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
+        if let Some(start) = &self.0 {
+            write!(f, "{}", Flat(start, sort))?;
         }
+        write!(f, "{}", self.1)?;
+        if let Some(end) = &self.2 {
+            write!(f, "{}", Flat(end, sort))?;
+        }
+
+        Ok(())
     }
 }
 
+impl<'a> fmt::Display for ItemRange<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RangeLimits {
+    /// The range excludes its end (`..`).
+    HalfOpen,
+    /// The range includes its end (`..=`).
+    Closed,
+}
+
+impl fmt::Display for RangeLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RangeLimits::HalfOpen => "..",
+            RangeLimits::Closed => "..=",
+        })
+    }
+}
+
+/// A set.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ItemSet<'a>(pub BTreeSet<AstItem<'a>>);
 
-impl<'a> fmt::Display for ItemSet<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl<'a> ItemSet<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemSet<'static> {
+        ItemSet(self.0.into_iter().map(AstItem::into_owned).collect())
+    }
+}
+
+impl<'a> ItemSet<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
+        let mut entries = self.0.iter().collect::<Vec<_>>();
+        if sort {
+            entries.sort_by(|lhs, rhs| natural_cmp(lhs, rhs));
+        }
+
         f.debug_set()
-            .entries(self.0.iter().map(DebugToDisplay))
+            .entries(entries.into_iter().map(|x| DebugToDisplay(Flat(x, sort))))
             .finish()
     }
 }
 
+impl<'a> fmt::Display for ItemSet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+/// A struct with named fields.
+///
+/// The last field is set when the struct hides some of its fields (`Foo { a: 1, .. }`).
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct ItemStruct<'a>(pub &'a str, pub BTreeMap<&'a str, AstItem<'a>>);
+pub struct ItemStruct<'a>(
+    pub Cow<'a, str>,
+    pub BTreeMap<Cow<'a, str>, AstItem<'a>>,
+    pub bool,
+);
 
-impl<'a> fmt::Display for ItemStruct<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut fmt = f.debug_struct(self.0);
+impl<'a> ItemStruct<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemStruct<'static> {
+        ItemStruct(
+            Cow::Owned(self.0.into_owned()),
+            self.1
+                .into_iter()
+                .map(|(k, v)| (Cow::Owned(k.into_owned()), v.into_owned()))
+                .collect(),
+            self.2,
+        )
+    }
+}
+
+impl<'a> ItemStruct<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
+        // `debug_struct` would print a lone name, which reads back as a unit struct.
+        if self.1.is_empty() && !self.2 {
+            return write!(f, "{} {{}}", self.0);
+        }
+
+        let mut fmt = f.debug_struct(&self.0);
         for (k, v) in &self.1 {
-            fmt.field(k, &DebugToDisplay(v));
+            fmt.field(k, &DebugToDisplay(Flat(v, sort)));
         }
-        fmt.finish()
+        if self.2 {
+            fmt.finish_non_exhaustive()
+        } else {
+            fmt.finish()
+        }
+    }
+}
+
+impl<'a> fmt::Display for ItemStruct<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
     }
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ItemTuple<'a>(pub Vec<AstItem<'a>>);
 
-impl<'a> fmt::Display for ItemTuple<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl<'a> ItemTuple<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemTuple<'static> {
+        ItemTuple(self.0.into_iter().map(AstItem::into_owned).collect())
+    }
+}
+
+impl<'a> ItemTuple<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
         if self.0.is_empty() {
             f.debug_tuple("()").finish()
         } else {
             let mut fmt = f.debug_tuple("");
             for field in &self.0 {
-                fmt.field(&DebugToDisplay(field));
+                fmt.field(&DebugToDisplay(Flat(field, sort)));
             }
             fmt.finish()
         }
     }
 }
 
+impl<'a> fmt::Display for ItemTuple<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct ItemTupleStruct<'a>(pub &'a str, pub Vec<AstItem<'a>>);
+pub struct ItemTupleStruct<'a>(pub Cow<'a, str>, pub Vec<AstItem<'a>>);
 
-impl<'a> fmt::Display for ItemTupleStruct<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut fmt = f.debug_tuple(self.0);
+impl<'a> ItemTupleStruct<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemTupleStruct<'static> {
+        ItemTupleStruct(
+            Cow::Owned(self.0.into_owned()),
+            self.1.into_iter().map(AstItem::into_owned).collect(),
+        )
+    }
+}
+
+impl<'a> ItemTupleStruct<'a> {
+    fn write(&self, f: &mut fmt::Formatter, sort: bool) -> fmt::Result {
+        // `debug_tuple` would print a lone name, which reads back as a unit struct.
+        if self.1.is_empty() {
+            return write!(f, "{}()", self.0);
+        }
+
+        let mut fmt = f.debug_tuple(&self.0);
         for field in &self.1 {
-            fmt.field(&DebugToDisplay(field));
+            fmt.field(&DebugToDisplay(Flat(field, sort)));
         }
         fmt.finish()
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct ItemUnitStruct<'a>(pub &'a str);
+impl<'a> fmt::Display for ItemTupleStruct<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ItemUnitStruct<'a>(pub Cow<'a, str>);
+
+impl<'a> ItemUnitStruct<'a> {
+    /// See [`AstItem::into_owned`].
+    pub fn into_owned(self) -> ItemUnitStruct<'static> {
+        ItemUnitStruct(Cow::Owned(self.0.into_owned()))
+    }
+}
 
 impl<'a> fmt::Display for ItemUnitStruct<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct(self.0).finish()
+        f.debug_struct(&self.0).finish()
     }
 }
+
+/// Formats a value on a single line like its `Display` implementation, but with the entries of its
+/// maps and sets sorted by [`natural_cmp`] when the second field is set.
+pub(super) struct Flat<'b, 'a>(pub &'b AstItem<'a>, pub bool);
+
+impl<'b, 'a> fmt::Display for Flat<'b, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sort = self.1;
+        match self.0 {
+            AstItem::ItemList(x) => x.write(f, sort),
+            AstItem::ItemMap(x) => x.write(f, sort),
+            AstItem::ItemOption(x) => x.write(f, sort),
+            AstItem::ItemRange(x) => x.write(f, sort),
+            AstItem::ItemSet(x) => x.write(f, sort),
+            AstItem::ItemStruct(x) => x.write(f, sort),
+            AstItem::ItemTuple(x) => x.write(f, sort),
+            AstItem::ItemTupleStruct(x) => x.write(f, sort),
+            x => x.fmt(f),
+        }
+    }
+}
+
+/// Numbers by value and strings by contents, falling back to the order of the AST.
+pub(super) fn natural_cmp(lhs: &AstItem, rhs: &AstItem) -> Ordering {
+    let ordering = match (lhs, rhs) {
+        (AstItem::ValueNum(l), AstItem::ValueNum(r)) => {
+            match (l.0.parse::<f64>(), r.0.parse::<f64>()) {
+                (Ok(l), Ok(r)) => l.total_cmp(&r),
+                _ => Ordering::Equal,
+            }
+        }
+        (AstItem::ValueStr(l), AstItem::ValueStr(r)) => l.unescape().cmp(&r.unescape()),
+        _ => Ordering::Equal,
+    };
+
+    ordering.then_with(|| lhs.cmp(rhs))
+}
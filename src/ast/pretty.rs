@@ -0,0 +1,201 @@
+use super::{
+    language::{natural_cmp, Flat},
+    AstItem,
+};
+use std::fmt::{self, Write};
+
+/// How to lay out a value with [`Pretty`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct PrettyConfig {
+    /// Number of spaces per level of nesting.
+    pub indent: usize,
+    /// Keep the values which fit within this many columns on a single line. When missing, every
+    /// non-empty value is broken up exactly like `{:#?}` does.
+    pub width: Option<usize>,
+    /// Sort map keys and set entries by their value (numbers numerically, strings by their
+    /// contents) instead of by their text.
+    pub sort: bool,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        Self {
+            indent: 4,
+            width: None,
+            sort: false,
+        }
+    }
+}
+
+/// Formats a value over multiple lines, in the style of `{:#?}`.
+pub struct Pretty<'a, 'b>(pub &'b AstItem<'a>, pub &'b PrettyConfig);
+
+impl<'a, 'b> fmt::Display for Pretty<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer {
+            config: self.1,
+            out: String::new(),
+        };
+        printer.item(self.0, 0, 0);
+
+        f.write_str(&printer.out)
+    }
+}
+
+/// A line within a broken up value.
+enum Entry<'b, 'a> {
+    Value(&'b AstItem<'a>),
+    Pair(&'b AstItem<'a>, &'b AstItem<'a>),
+    Field(&'a str, &'b AstItem<'a>),
+    /// The `..` of a non-exhaustive struct.
+    Rest,
+}
+
+struct Printer<'c> {
+    config: &'c PrettyConfig,
+    out: String,
+}
+
+impl<'c> Printer<'c> {
+    /// Write a value which will be followed by `trailing` characters on its last line.
+    fn item(&mut self, item: &AstItem, depth: usize, trailing: usize) {
+        if let Some(width) = self.config.width {
+            let column = self
+                .out
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count();
+            let mut flat = Bounded {
+                out: String::new(),
+                len: 0,
+                max_len: width.saturating_sub(column + trailing),
+            };
+            if self.flat(&mut flat, item).is_ok() {
+                self.out.push_str(&flat.out);
+                return;
+            }
+        }
+
+        match item {
+            AstItem::ItemList(x) if !x.0.is_empty() => {
+                self.block("[", x.0.iter().map(Entry::Value), "]", depth);
+            }
+            AstItem::ItemMap(x) if !x.0.is_empty() => {
+                let entries = self.sorted(x.0.iter().collect(), |&(k, _)| k);
+                self.block("{", entries.map(|(k, v)| Entry::Pair(k, v)), "}", depth);
+            }
+            AstItem::ItemOption(x) => match x.0.as_deref() {
+                Some(x) => self.block("Some(", [Entry::Value(x)].into_iter(), ")", depth),
+                None => self.out.push_str("None"),
+            },
+            AstItem::ItemRange(x) => {
+                if let Some(start) = &x.0 {
+                    self.item(start, depth, 0);
+                }
+                write!(self.out, "{}", x.1).unwrap();
+                if let Some(end) = &x.2 {
+                    self.item(end, depth, trailing);
+                }
+            }
+            AstItem::ItemSet(x) if !x.0.is_empty() => {
+                let entries = self.sorted(x.0.iter().collect(), |&x| x);
+                self.block("{", entries.map(Entry::Value), "}", depth);
+            }
+            AstItem::ItemStruct(x) if !x.1.is_empty() => {
+                let fields = x.1.iter().map(|(k, v)| Entry::Field(k, v));
+                let rest = x.2.then_some(Entry::Rest);
+                let open = format!("{} {{", x.0);
+                self.block(&open, fields.chain(rest), "}", depth);
+            }
+            AstItem::ItemTuple(x) if !x.0.is_empty() => {
+                self.block("(", x.0.iter().map(Entry::Value), ")", depth);
+            }
+            AstItem::ItemTupleStruct(x) if !x.1.is_empty() => {
+                let open = format!("{}(", x.0);
+                self.block(&open, x.1.iter().map(Entry::Value), ")", depth);
+            }
+            _ => {
+                let mut flat = String::new();
+                self.flat(&mut flat, item).unwrap();
+                self.out.push_str(&flat);
+            }
+        }
+    }
+
+    /// Write a value broken up with one entry per line.
+    fn block<'b, 'a: 'b>(
+        &mut self,
+        open: &str,
+        entries: impl Iterator<Item = Entry<'b, 'a>>,
+        close: &str,
+        depth: usize,
+    ) {
+        let indent = " ".repeat(self.config.indent * (depth + 1));
+
+        self.out.push_str(open);
+        self.out.push('\n');
+        for entry in entries {
+            self.out.push_str(&indent);
+            match entry {
+                Entry::Value(x) => self.item(x, depth + 1, 1),
+                Entry::Pair(k, v) => {
+                    self.item(k, depth + 1, 2);
+                    self.out.push_str(": ");
+                    self.item(v, depth + 1, 1);
+                }
+                Entry::Field(k, v) => {
+                    self.out.push_str(k);
+                    self.out.push_str(": ");
+                    self.item(v, depth + 1, 1);
+                }
+                Entry::Rest => {
+                    self.out.push_str("..\n");
+                    continue;
+                }
+            }
+            self.out.push_str(",\n");
+        }
+        self.out.push_str(&indent[..self.config.indent * depth]);
+        self.out.push_str(close);
+    }
+
+    /// Write a value on a single line, in the same way as its `Display` implementation.
+    fn flat(&self, f: &mut impl Write, item: &AstItem) -> fmt::Result {
+        write!(f, "{}", Flat(item, self.config.sort))
+    }
+
+    /// Order the entries of a map or a set as requested.
+    fn sorted<'b, 'a: 'b, T>(
+        &self,
+        mut entries: Vec<T>,
+        key: impl Fn(&T) -> &'b AstItem<'a>,
+    ) -> impl Iterator<Item = T> {
+        if self.config.sort {
+            entries.sort_by(|lhs, rhs| natural_cmp(key(lhs), key(rhs)));
+        }
+
+        entries.into_iter()
+    }
+}
+
+/// A writer which fails once more than `max_len` characters are written to it.
+struct Bounded {
+    out: String,
+    len: usize,
+    max_len: usize,
+}
+
+impl Write for Bounded {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.len += s.chars().count();
+        if self.len > self.max_len {
+            return Err(fmt::Error);
+        }
+
+        self.out.push_str(s);
+        Ok(())
+    }
+}
@@ -1,4 +1,10 @@
-use std::fmt;
+use std::{
+    borrow::Cow,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::Chars,
+    time::Duration,
+};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ValueBool(pub bool);
@@ -9,20 +15,315 @@ impl fmt::Display for ValueBool {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct ValueNum<'a>(pub &'a str);
+/// A byte string literal (`b"..."`), as printed by `bytes::Bytes` and friends.
+///
+/// The slice is kept escaped, exactly as it appears in the input.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueBytes<'a>(pub Cow<'a, str>);
+
+impl<'a> ValueBytes<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueBytes<'static> {
+        ValueBytes(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Decode the escape sequences into the actual bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.0.len());
+        let mut chars = self.0.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => match unescape_next(&mut chars) {
+                    Some(x) if x <= 0xFF => bytes.push(x as u8),
+                    Some(x) => bytes.extend_from_slice(
+                        char::from_u32(x)
+                            .unwrap_or(char::REPLACEMENT_CHARACTER)
+                            .encode_utf8(&mut [0; 4])
+                            .as_bytes(),
+                    ),
+                    None => {}
+                },
+                ch => bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+
+        bytes
+    }
+}
+
+impl<'a> fmt::Display for ValueBytes<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b\"{}\"", self.0)
+    }
+}
+
+/// A duration (`1.5s`, `250ms`, `3µs`, `12ns`), as printed by `std::time::Duration`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueDuration<'a>(pub Cow<'a, str>);
+
+impl<'a> ValueDuration<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueDuration<'static> {
+        ValueDuration(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Convert the literal into an actual duration, or `None` if it isn't valid.
+    pub fn to_duration(&self) -> Option<Duration> {
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+        let (value, mut scale) = [
+            ("ns", 1),
+            ("µs", 1_000),
+            ("ms", 1_000_000),
+            ("s", NANOS_PER_SEC),
+        ]
+        .into_iter()
+        .find_map(|(suffix, scale)| Some((self.0.strip_suffix(suffix)?, scale)))?;
+        let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+
+        let mut nanos = int.parse::<u128>().ok()? * scale;
+        for digit in frac.chars() {
+            scale /= 10;
+            nanos += u128::from(digit.to_digit(10)?) * scale;
+        }
+
+        Some(Duration::new(
+            u64::try_from(nanos / NANOS_PER_SEC).ok()?,
+            (nanos % NANOS_PER_SEC) as u32,
+        ))
+    }
+}
+
+impl<'a> fmt::Display for ValueDuration<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// An IPv4 or IPv6 address (`127.0.0.1`, `::1`).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueIpAddr<'a>(pub Cow<'a, str>);
+
+impl<'a> ValueIpAddr<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueIpAddr<'static> {
+        ValueIpAddr(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Convert the literal into an actual address, or `None` if it isn't valid.
+    pub fn to_ip_addr(&self) -> Option<IpAddr> {
+        self.0.parse().ok()
+    }
+}
+
+impl<'a> fmt::Display for ValueIpAddr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueNum<'a>(pub Cow<'a, str>);
+
+impl<'a> ValueNum<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueNum<'static> {
+        ValueNum(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Convert the literal into an integer, or `None` if it isn't one (or doesn't fit).
+    ///
+    /// The way other formats write integers is accepted too: `+1`, `1_000`, `0x1F`, `0o17`, `0b11`.
+    pub fn to_i128(&self) -> Option<i128> {
+        let text = self.0.replace('_', "");
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        let (radix, digits) = [("0x", 16), ("0o", 8), ("0b", 2)]
+            .into_iter()
+            .find_map(|(prefix, radix)| Some((radix, digits.strip_prefix(prefix)?)))
+            .unwrap_or((10, digits));
+        if digits.starts_with(['+', '-']) {
+            return None;
+        }
+
+        let value = i128::from_str_radix(digits, radix).ok()?;
+        Some(if negative { -value } else { value })
+    }
+
+    /// Convert the literal into a float, or `None` if it isn't a number.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self.to_i128() {
+            Some(value) => Some(value as f64),
+            None => self.0.replace('_', "").parse().ok(),
+        }
+    }
+}
 
 impl<'a> fmt::Display for ValueNum<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.0)
+        f.write_str(&self.0)
+    }
+}
+
+/// A value that only makes sense within a single run of a program, like an address or a lock
+/// that couldn't be inspected.
+///
+/// The slice is the whole token (`0x7ffd5a3c1e40`, `<locked>`, `{closure}`...).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueOpaque<'a>(pub OpaqueKind, pub Cow<'a, str>);
+
+impl<'a> ValueOpaque<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueOpaque<'static> {
+        ValueOpaque(self.0, Cow::Owned(self.1.into_owned()))
+    }
+}
+
+impl<'a> fmt::Display for ValueOpaque<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.1)
     }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct ValueStr<'a>(pub &'a str);
+#[non_exhaustive]
+pub enum OpaqueKind {
+    /// A pointer or address (`0x7ffd5a3c1e40`).
+    Pointer,
+    /// A placeholder printed instead of an inaccessible value (`<locked>`, `<poisoned>`...).
+    Placeholder,
+    /// A closure or async block (`{closure}`, `{closure#0}`, `{async block}`...).
+    Closure,
+}
+
+/// A socket address (`10.0.0.1:8080`, `[::1]:8080`).
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueSocketAddr<'a>(pub Cow<'a, str>);
+
+impl<'a> ValueSocketAddr<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueSocketAddr<'static> {
+        ValueSocketAddr(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Convert the literal into an actual address, or `None` if it isn't valid.
+    pub fn to_socket_addr(&self) -> Option<SocketAddr> {
+        self.0.parse().ok()
+    }
+}
+
+impl<'a> fmt::Display for ValueSocketAddr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ValueStr<'a>(pub Cow<'a, str>, pub StrKind);
+
+impl<'a> ValueStr<'a> {
+    /// See [`AstItem::into_owned`](crate::AstItem::into_owned).
+    pub fn into_owned(self) -> ValueStr<'static> {
+        ValueStr(Cow::Owned(self.0.into_owned()), self.1)
+    }
+
+    /// Build an escaped string literal holding `text`, the reverse of [`ValueStr::unescape`].
+    pub fn escape(text: &str) -> ValueStr<'static> {
+        // `Debug` escapes strings the way the parser expects, quotes included.
+        let escaped = format!("{text:?}");
+        ValueStr(
+            escaped[1..escaped.len() - 1].to_owned().into(),
+            StrKind::Escaped,
+        )
+    }
+
+    /// Decode the escape sequences (if any) into the actual string.
+    pub fn unescape(&self) -> Cow<'_, str> {
+        if matches!(self.1, StrKind::Raw(_)) || !self.0.contains('\\') {
+            return Cow::Borrowed(&self.0);
+        }
+
+        let mut value = String::with_capacity(self.0.len());
+        let mut chars = self.0.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => value.push(
+                    unescape_next(&mut chars)
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER),
+                ),
+                ch => value.push(ch),
+            }
+        }
+
+        Cow::Owned(value)
+    }
+}
 
 impl<'a> fmt::Display for ValueStr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\"{}\"", self.0)
+        match self.1 {
+            StrKind::Escaped => write!(f, "\"{}\"", self.0),
+            StrKind::Raw(n) => {
+                let fence = "#".repeat(n);
+                write!(f, "r{fence}\"{}\"{fence}", self.0)
+            }
+        }
     }
 }
+
+/// How a string literal was delimited in the input.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum StrKind {
+    /// A regular `"..."` literal, which may contain escape sequences.
+    Escaped,
+    /// A raw `r#"..."#` literal fenced by the given number of `#`.
+    Raw(usize),
+}
+
+/// Decode the escape sequence following a backslash into its code point (or byte value for `\x`).
+fn unescape_next(chars: &mut Chars) -> Option<u32> {
+    Some(match chars.next()? {
+        'n' => '\n' as u32,
+        'r' => '\r' as u32,
+        't' => '\t' as u32,
+        '0' => 0,
+        'x' => {
+            let value = u32::from_str_radix(chars.as_str().get(..2)?, 16).ok()?;
+            chars.nth(1);
+            value
+        }
+        // Not produced by `Debug`, but used by JSON.
+        'b' => 0x08,
+        'f' => 0x0c,
+        'u' => match chars.as_str().strip_prefix('{') {
+            Some(rest) => {
+                let end = rest.find('}')?;
+                let value = u32::from_str_radix(&rest[..end], 16).ok()?;
+                *chars = rest[end + 1..].chars();
+                value
+            }
+            // JSON uses exactly four digits, and a surrogate pair for larger code points.
+            None => {
+                let value = u32::from_str_radix(chars.as_str().get(..4)?, 16).ok()?;
+                chars.nth(3);
+
+                let low = chars
+                    .as_str()
+                    .strip_prefix("\\u")
+                    .and_then(|x| u32::from_str_radix(x.get(..4)?, 16).ok())
+                    .filter(|x| (0xdc00..0xe000).contains(x));
+                match low {
+                    Some(low) if (0xd800..0xdc00).contains(&value) => {
+                        chars.nth(5);
+                        0x10000 + ((value - 0xd800) << 10) + (low - 0xdc00)
+                    }
+                    _ => value,
+                }
+            }
+        },
+        ch => ch as u32,
+    })
+}
@@ -0,0 +1,122 @@
+//! Generators of random trees for property-based tests.
+//!
+//! Only trees the parser could produce are generated: no empty maps or sets (they're `ItemEmpty`),
+//! no names the parser reads as something else (`None`, `true`...), no closures in single braces
+//! (a set holding only `{closure}` prints as `{{closure}}`) and no sets holding only `..` (which
+//! print as a struct without a name or fields, `{ .. }`).
+
+use super::*;
+use proptest::{collection, prelude::*};
+use std::{
+    borrow::Cow,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+/// Any tree.
+pub fn ast_item() -> impl Strategy<Value = AstItem<'static>> {
+    leaf().prop_recursive(4, 64, 6, |inner| {
+        prop_oneof![
+            collection::vec(inner.clone(), 0..6).prop_map(|x| ItemList(x).into()),
+            collection::btree_map(inner.clone(), inner.clone(), 1..6)
+                .prop_map(|x| ItemMap(x).into()),
+            inner
+                .clone()
+                .prop_map(|x| ItemOption(Some(Box::new(x))).into()),
+            collection::btree_set(inner.clone(), 1..6)
+                .prop_filter("ambiguous set", |x| {
+                    !matches!(
+                        x.first(),
+                        Some(AstItem::ItemRange(ItemRange(None, RangeLimits::HalfOpen, None)))
+                            if x.len() == 1
+                    )
+                })
+                .prop_map(|x| ItemSet(x).into()),
+            (
+                type_name(),
+                collection::btree_map(field_name(), inner.clone(), 0..6),
+                any::<bool>()
+            )
+                .prop_map(|(name, fields, non_exhaustive)| {
+                    ItemStruct(name, fields, non_exhaustive).into()
+                }),
+            collection::vec(inner.clone(), 0..6).prop_map(|x| ItemTuple(x).into()),
+            (type_name(), collection::vec(inner, 0..6))
+                .prop_map(|(name, items)| ItemTupleStruct(name, items).into()),
+        ]
+    })
+}
+
+/// Any value without children.
+fn leaf() -> impl Strategy<Value = AstItem<'static>> {
+    prop_oneof![
+        Just(ItemEmpty.into()),
+        Just(ItemOption(None).into()),
+        range(),
+        bound(),
+        escaped_str().prop_map(|x| ValueBytes(x).into()),
+        value_opaque().prop_map(AstItem::from),
+        (any::<Ipv4Addr>(), any::<u16>())
+            .prop_map(|(ip, port)| ValueSocketAddr(format!("{ip}:{port}").into()).into()),
+        (any::<Ipv6Addr>(), any::<u16>())
+            .prop_map(|(ip, port)| ValueSocketAddr(format!("[{ip}]:{port}").into()).into()),
+    ]
+}
+
+fn range() -> impl Strategy<Value = AstItem<'static>> {
+    let limits = prop_oneof![Just(RangeLimits::HalfOpen), Just(RangeLimits::Closed)];
+    (
+        proptest::option::of(bound()),
+        limits,
+        proptest::option::of(bound()),
+    )
+        .prop_map(|(start, limits, end)| {
+            ItemRange(start.map(Box::new), limits, end.map(Box::new)).into()
+        })
+}
+
+/// A value which may be the start or the end of a range.
+fn bound() -> impl Strategy<Value = AstItem<'static>> {
+    prop_oneof![
+        type_name().prop_map(|x| ItemUnitStruct(x).into()),
+        any::<bool>().prop_map(|x| ValueBool(x).into()),
+        "[0-9]{1,3}(\\.[0-9]{1,3})?(ns|µs|ms|s)".prop_map(|x| ValueDuration(x.into()).into()),
+        "-?[0-9]{1,5}(\\.[0-9]{1,3})?(e-?[0-9]{1,2})?|-inf".prop_map(|x| ValueNum(x.into()).into()),
+        escaped_str().prop_map(|x| ValueStr(x, StrKind::Escaped).into()),
+        any::<Ipv4Addr>().prop_map(|x| ValueIpAddr(x.to_string().into()).into()),
+        any::<Ipv6Addr>().prop_map(|x| ValueIpAddr(x.to_string().into()).into()),
+        ("[a-z \\\\#]{0,8}", 0..6usize)
+            .prop_map(|(x, n)| ValueStr(x.into(), StrKind::Raw(n)).into()),
+    ]
+}
+
+fn value_opaque() -> impl Strategy<Value = ValueOpaque<'static>> {
+    prop_oneof![
+        "0x[0-9a-f]{1,12}".prop_map(|x| ValueOpaque(OpaqueKind::Pointer, x.into())),
+        "<[a-z ]{1,8}>".prop_map(|x| ValueOpaque(OpaqueKind::Placeholder, x.into())),
+        prop_oneof![
+            Just("{{closure}}"),
+            Just("{{closure#0}}"),
+            Just("{{async block}}"),
+            Just("{{async fn body#1}}"),
+        ]
+        .prop_map(|x| ValueOpaque(OpaqueKind::Closure, x.into())),
+    ]
+}
+
+/// The contents of a string literal, with valid escape sequences.
+fn escaped_str() -> impl Strategy<Value = Cow<'static, str>> {
+    "([a-zA-Z0-9 ]|\\\\[nrt0\"\\\\]|\\\\x[0-7][0-9a-f]|\\\\u\\{[0-9a-f]{1,4}\\})*"
+        .prop_map(Cow::Owned)
+}
+
+fn type_name() -> impl Strategy<Value = Cow<'static, str>> {
+    "[A-Z][a-zA-Z0-9_]{0,6}"
+        .prop_filter("keyword", |x| !matches!(x.as_str(), "None" | "Some"))
+        .prop_map(Cow::Owned)
+}
+
+fn field_name() -> impl Strategy<Value = Cow<'static, str>> {
+    "[a-z_][a-z0-9_]{0,6}"
+        .prop_filter("keyword", |x| !matches!(x.as_str(), "_" | "true" | "false"))
+        .prop_map(Cow::Owned)
+}
@@ -0,0 +1,237 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use debug_diff::{
+    ast::ValueDuration,
+    normalize::Rules,
+    path::{self, PathPattern},
+    query::Query,
+    DiffConfig,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Find the differences between two values printed using `Debug`.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct CmdLine {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// File with the actual value.
+    #[arg(required = true)]
+    pub left: Option<PathBuf>,
+    /// File with the expected value.
+    #[arg(required = true)]
+    pub right: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub diff: DiffArgs,
+
+    /// Format of the actual value (guessed from the extension of the file if missing).
+    #[arg(long, value_enum, conflicts_with_all = ["document", "log", "dbg", "trace"])]
+    pub left_format: Option<InputFormat>,
+    /// Format of the expected value (guessed from the extension of the file if missing).
+    #[arg(long, value_enum, conflicts_with_all = ["document", "log", "dbg", "trace"])]
+    pub right_format: Option<InputFormat>,
+    /// Only compare the values selected by this query (`.users[?name == "bob"]`), pairing those at
+    /// the same path on both sides.
+    #[arg(long, value_name = "QUERY", conflicts_with_all = ["document", "log", "dbg", "trace"])]
+    pub only: Option<Query>,
+
+    /// Treat each file as a sequence of values (one snapshot per step, for example) and compare
+    /// both sequences.
+    #[arg(long, conflicts_with_all = ["log", "dbg", "trace"])]
+    pub document: bool,
+    /// Ignore the lines which don't hold a value when reading a sequence of values.
+    #[arg(long, requires = "document")]
+    pub skip_text: bool,
+
+    /// Extract the values embedded in two logs and compare the selected ones.
+    #[arg(long, conflicts_with_all = ["dbg", "trace"])]
+    pub log: bool,
+    /// Only compare the values logged by this target (or one of its submodules).
+    #[arg(long, requires = "log")]
+    pub target: Option<String>,
+    /// Only compare the values with this name (`key=value` or `key: value`).
+    #[arg(long, requires = "log")]
+    pub key: Option<String>,
+    /// Only compare the nth selected value (starting at 0).
+    #[arg(long, requires = "log")]
+    pub nth: Option<usize>,
+
+    /// Compare the values printed by `dbg!` in two runs, matching them by location and expression.
+    #[arg(long, conflicts_with = "trace")]
+    pub dbg: bool,
+
+    /// Compare the events printed by the `fmt` layer of `tracing-subscriber` in two runs, one by
+    /// one.
+    #[arg(long)]
+    pub trace: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show the differences of every failed `assert_eq!` in the output of `cargo test`.
+    Assert {
+        /// File with the output of the tests (standard input if missing).
+        input: Option<PathBuf>,
+
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+    /// Convert a value into another format, for tools like `jq`.
+    Convert {
+        /// File with the value (standard input if missing).
+        input: Option<PathBuf>,
+
+        /// The format to convert into.
+        #[arg(long, value_enum)]
+        to: Format,
+    },
+    /// Pretty-print a value in the style of `{:#?}`.
+    Fmt {
+        /// File with the value (standard input if missing).
+        input: Option<PathBuf>,
+
+        /// Number of spaces per level of nesting.
+        #[arg(long, default_value_t = 4)]
+        indent: usize,
+        /// Keep the values which fit within this many columns on a single line.
+        #[arg(long)]
+        width: Option<usize>,
+        /// Sort map keys and set entries by their value (numbers numerically, strings by their
+        /// contents) instead of by their text.
+        #[arg(long)]
+        sort: bool,
+    },
+    /// Print the values selected by a query (`..Session.id`), along with their path.
+    Query {
+        /// The query, as described in the documentation of the `query` module.
+        query: Query,
+        /// File with the value (standard input if missing).
+        input: Option<PathBuf>,
+
+        /// Format of the value (guessed from the extension of the file if missing).
+        #[arg(long, value_enum)]
+        format: Option<InputFormat>,
+    },
+    /// Accept or reject the snapshots which no longer match, one by one.
+    Review {
+        /// Directory to look for pending snapshots in.
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum InputFormat {
+    /// Output of `Debug`.
+    Debug,
+    Json,
+    Ron,
+}
+
+impl InputFormat {
+    /// Guess the format of a file from its extension, defaulting to the output of `Debug`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => InputFormat::Json,
+            Some("ron") => InputFormat::Ron,
+            _ => InputFormat::Debug,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Json,
+    Yaml,
+    Ron,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Compare opaque values (pointers, `<locked>`, closures...) by their text instead of ignoring
+    /// them.
+    #[arg(long)]
+    pub strict_opaque: bool,
+    /// Compare durations and addresses by their value instead of by their text (`1000ms == 1s`).
+    #[arg(long)]
+    pub semantic: bool,
+    /// Consider durations equal if they're at most this far apart (`5ms`, `1.5s`...).
+    #[arg(long, value_parser = parse_duration)]
+    pub duration_tolerance: Option<Duration>,
+    /// Don't compare the values at the paths matching this pattern (`.requests[*].timestamp`).
+    #[arg(long, value_name = "PATTERN")]
+    pub ignore: Vec<PathPattern>,
+    /// Don't compare the values at the paths matching the patterns in this file, one per line.
+    #[arg(long, value_name = "FILE", value_parser = read_patterns)]
+    pub ignore_file: Vec<PatternFile>,
+    /// Compare the lists at the paths matching this pattern ignoring the order of their items
+    /// (`.tags`, `.users[*].roles`).
+    #[arg(long, value_name = "PATTERN")]
+    pub unordered: Vec<PathPattern>,
+    /// Match the items of the lists at the paths matching a pattern by one of their fields rather
+    /// than by their position (`.orders=id`).
+    #[arg(long, value_name = "PATTERN=FIELD", value_parser = parse_list_key)]
+    pub list_key: Vec<(PathPattern, String)>,
+    /// Normalize both sides with the rules in this YAML file before comparing them (see the
+    /// documentation of the `normalize` module).
+    #[arg(long, value_name = "FILE", value_parser = read_rules)]
+    pub rules: Option<Rules>,
+}
+
+/// The patterns read from a file.
+#[derive(Clone, Debug)]
+pub struct PatternFile(pub Vec<PathPattern>);
+
+impl DiffArgs {
+    pub fn to_config(&self) -> DiffConfig {
+        let mut config = DiffConfig::default();
+        config.strict_opaque = self.strict_opaque;
+        config.semantic = self.semantic;
+        config.duration_tolerance = self.duration_tolerance;
+        config.ignore = self.ignore.clone();
+        for file in &self.ignore_file {
+            config.ignore.extend(file.0.iter().cloned());
+        }
+        config.unordered = self.unordered.clone();
+        config.list_keys = self.list_key.clone();
+
+        config
+    }
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    ValueDuration(value.into())
+        .to_duration()
+        .ok_or_else(|| format!("invalid duration `{value}`"))
+}
+
+fn parse_list_key(value: &str) -> Result<(PathPattern, String), String> {
+    // Field names never hold a `=`, unlike the keys within a pattern.
+    let (pattern, field) = value
+        .rsplit_once('=')
+        .filter(|(_, field)| !field.is_empty())
+        .ok_or_else(|| format!("expected `PATTERN=FIELD`, got `{value}`"))?;
+    let pattern = pattern
+        .parse::<PathPattern>()
+        .map_err(|error| error.to_string())?;
+    Ok((pattern, field.to_owned()))
+}
+
+fn read_patterns(path: &str) -> Result<PatternFile, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let patterns = path::parse_patterns(&text).map_err(|error| error.to_string())?;
+    Ok(PatternFile(patterns))
+}
+
+fn read_rules(path: &str) -> Result<Rules, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    Rules::from_yaml(&text).map_err(|error| error.to_string())
+}
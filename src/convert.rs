@@ -0,0 +1,362 @@
+//! Conversion of values into JSON, YAML and RON.
+//!
+//! JSON and YAML go through a JSON data model, whose mapping follows what `serde` does by default
+//! wherever possible:
+//!
+//! | Value                        | Converted into                                                |
+//! |------------------------------|---------------------------------------------------------------|
+//! | `Foo { a: 1 }`               | `{"$type": "Foo", "a": 1}` (`..` is dropped)                  |
+//! | `{ a: 1 }`                   | `{"a": 1}`                                                    |
+//! | `Foo(1)`, `Foo(1, 2)`        | `{"Foo": 1}`, `{"Foo": [1, 2]}` (like an enum variant)        |
+//! | `Foo`                        | `"Foo"`                                                       |
+//! | `Some(1)`, `None`            | `1`, `null`                                                   |
+//! | `[1, 2]`, `{1, 2}`, `(1, 2)` | `[1, 2]`                                                      |
+//! | `{"a": 1}`                   | `{"a": 1}`                                                    |
+//! | `{1: 2}`                     | `[{"key": 1, "value": 2}]` (what jq's `from_entries` expects) |
+//! | `{}`                         | `{}`                                                          |
+//! | `1..=2`                      | `{"$type": "RangeInclusive", "start": 1, "end": 2}`           |
+//! | `1.5s`                       | `{"secs": 1, "nanos": 500000000}`                             |
+//! | `b"ab"`                      | `[97, 98]`                                                    |
+//! | `"a\n"`                      | `"a\n"` (unescaped)                                           |
+//! | numbers                      | numbers (strings if they don't fit in 64 bits)                |
+//! | addresses, opaque values     | strings                                                       |
+//!
+//! RON is written straight from the value instead, since it has structs of its own:
+//!
+//! | Value                    | Converted into                |
+//! |--------------------------|-------------------------------|
+//! | `Foo { a: 1 }`           | `Foo(a: 1)` (`..` is dropped) |
+//! | `Foo(1)`, `Foo`          | `Foo(1)`, `Foo`               |
+//! | `Some(1)`, `None`        | `Some(1)`, `None`             |
+//! | `[1, 2]`, `{1, 2}`       | `[1, 2]`                      |
+//! | `(1, 2)`                 | `(1, 2)`                      |
+//! | `{1: 2}`, `{}`           | `{1: 2}`, `{}`                |
+//! | `1..`                    | `Range(start: 1, end: None)`  |
+//! | `1.5s`                   | `(secs: 1, nanos: 500000000)` |
+//! | `b"ab"`                  | `[97, 98]`                    |
+//! | `r"a\"`                  | `"a\\"` (escaped again)       |
+//! | numbers                  | numbers                       |
+//! | addresses, opaque values | strings                       |
+
+use crate::ast::*;
+use serde_json::{json, Map, Number, Value};
+use std::borrow::Cow;
+
+/// Convert a value into JSON, as described above.
+pub fn to_value(item: &AstItem) -> Value {
+    match item {
+        AstItem::ItemEmpty(_) => Value::Object(Map::new()),
+        AstItem::ItemList(x) => Value::Array(x.0.iter().map(to_value).collect()),
+        AstItem::ItemMap(x) => {
+            let keys =
+                x.0.keys()
+                    .map(|k| match k {
+                        AstItem::ValueStr(k) => Some(k.unescape().into_owned()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+
+            match keys {
+                Some(keys) => {
+                    Value::Object(keys.into_iter().zip(x.0.values().map(to_value)).collect())
+                }
+                None => {
+                    x.0.iter()
+                        .map(|(k, v)| json!({ "key": to_value(k), "value": to_value(v) }))
+                        .collect()
+                }
+            }
+        }
+        AstItem::ItemOption(x) => x.0.as_deref().map_or(Value::Null, to_value),
+        AstItem::ItemRange(x) => {
+            let bound = |x: &Option<Box<AstItem>>| x.as_deref().map_or(Value::Null, to_value);
+            let name = match x.1 {
+                RangeLimits::HalfOpen => "Range",
+                RangeLimits::Closed => "RangeInclusive",
+            };
+
+            json!({ "$type": name, "start": bound(&x.0), "end": bound(&x.2) })
+        }
+        AstItem::ItemSet(x) => Value::Array(x.0.iter().map(to_value).collect()),
+        AstItem::ItemStruct(x) => {
+            let mut object = Map::new();
+            // Structs without a name are objects read from JSON or YAML.
+            if !x.0.is_empty() {
+                object.insert("$type".to_owned(), x.0.as_ref().into());
+            }
+            for (k, v) in &x.1 {
+                object.insert(k.to_string(), to_value(v));
+            }
+
+            Value::Object(object)
+        }
+        AstItem::ItemTuple(x) => Value::Array(x.0.iter().map(to_value).collect()),
+        AstItem::ItemTupleStruct(x) => {
+            let value = match x.1.as_slice() {
+                [x] => to_value(x),
+                items => Value::Array(items.iter().map(to_value).collect()),
+            };
+
+            json!({ x.0.as_ref(): value })
+        }
+        AstItem::ItemUnitStruct(x) => x.0.as_ref().into(),
+        AstItem::ValueBool(x) => x.0.into(),
+        AstItem::ValueBytes(x) => x.to_bytes().into(),
+        AstItem::ValueDuration(x) => match x.to_duration() {
+            Some(duration) => json!({
+                "secs": duration.as_secs(),
+                "nanos": duration.subsec_nanos(),
+            }),
+            None => x.0.as_ref().into(),
+        },
+        AstItem::ValueIpAddr(x) => x.0.as_ref().into(),
+        AstItem::ValueNum(x) => to_number(&x.0).map_or_else(|| x.0.as_ref().into(), Value::Number),
+        AstItem::ValueOpaque(x) => x.1.as_ref().into(),
+        AstItem::ValueSocketAddr(x) => x.0.as_ref().into(),
+        AstItem::ValueStr(x) => x.unescape().into(),
+    }
+}
+
+fn to_number(value: &str) -> Option<Number> {
+    if let Ok(value) = value.parse::<i64>() {
+        Some(value.into())
+    } else if let Ok(value) = value.parse::<u64>() {
+        Some(value.into())
+    } else if value.contains(['.', 'e', 'E']) {
+        Number::from_f64(value.parse().ok()?)
+    } else {
+        None
+    }
+}
+
+/// Write a value as pretty-printed RON, as described above.
+pub fn to_ron(item: &AstItem) -> String {
+    let mut out = String::new();
+    write_ron(&mut out, item, 0);
+    out
+}
+
+/// A child of a RON value: `value`, `name: value` or `key: value`.
+enum RonChild<'x, 'a> {
+    Item(&'x AstItem<'a>),
+    Field(&'x str, Cow<'x, AstItem<'a>>),
+    Entry(&'x AstItem<'a>, &'x AstItem<'a>),
+}
+
+fn write_ron(out: &mut String, item: &AstItem, depth: usize) {
+    match item {
+        AstItem::ItemEmpty(_) => out.push_str("{}"),
+        AstItem::ItemList(x) => {
+            write_ron_children(out, "[", x.0.iter().map(RonChild::Item), "]", depth)
+        }
+        AstItem::ItemMap(x) => {
+            let entries = x.0.iter().map(|(k, v)| RonChild::Entry(k, v));
+            write_ron_children(out, "{", entries, "}", depth);
+        }
+        AstItem::ItemOption(x) => match &x.0 {
+            Some(x) => {
+                out.push_str("Some(");
+                write_ron(out, x, depth);
+                out.push(')');
+            }
+            None => out.push_str("None"),
+        },
+        AstItem::ItemRange(x) => {
+            fn bound<'x, 'a>(x: &'x Option<Box<AstItem<'a>>>) -> Cow<'x, AstItem<'a>> {
+                x.as_deref()
+                    .map_or_else(|| Cow::Owned(ItemOption(None).into()), Cow::Borrowed)
+            }
+            let name = match x.1 {
+                RangeLimits::HalfOpen => "Range(",
+                RangeLimits::Closed => "RangeInclusive(",
+            };
+            let fields = [
+                RonChild::Field("start", bound(&x.0)),
+                RonChild::Field("end", bound(&x.2)),
+            ];
+            write_ron_children(out, name, fields, ")", depth);
+        }
+        AstItem::ItemSet(x) => {
+            write_ron_children(out, "[", x.0.iter().map(RonChild::Item), "]", depth)
+        }
+        AstItem::ItemStruct(x) => {
+            let fields =
+                x.1.iter()
+                    .map(|(k, v)| RonChild::Field(k, Cow::Borrowed(v)));
+            write_ron_children(out, &format!("{}(", x.0), fields, ")", depth);
+        }
+        AstItem::ItemTuple(x) => {
+            write_ron_children(out, "(", x.0.iter().map(RonChild::Item), ")", depth)
+        }
+        AstItem::ItemTupleStruct(x) => {
+            let items = x.1.iter().map(RonChild::Item);
+            write_ron_children(out, &format!("{}(", x.0), items, ")", depth);
+        }
+        AstItem::ItemUnitStruct(x) => out.push_str(&x.0),
+        AstItem::ValueBool(x) => out.push_str(if x.0 { "true" } else { "false" }),
+        AstItem::ValueBytes(x) => {
+            let bytes = x.to_bytes();
+            let bytes = bytes
+                .iter()
+                .map(|x| ValueNum(x.to_string().into()).into())
+                .collect::<Vec<AstItem>>();
+            write_ron_children(out, "[", bytes.iter().map(RonChild::Item), "]", depth);
+        }
+        AstItem::ValueDuration(x) => match x.to_duration() {
+            Some(duration) => {
+                let fields = [
+                    RonChild::Field(
+                        "secs",
+                        Cow::Owned(ValueNum(duration.as_secs().to_string().into()).into()),
+                    ),
+                    RonChild::Field(
+                        "nanos",
+                        Cow::Owned(ValueNum(duration.subsec_nanos().to_string().into()).into()),
+                    ),
+                ];
+                write_ron_children(out, "(", fields, ")", depth);
+            }
+            None => write_ron_str(out, &x.0),
+        },
+        AstItem::ValueIpAddr(x) => write_ron_str(out, &x.0),
+        AstItem::ValueNum(x) => out.push_str(&x.0),
+        AstItem::ValueOpaque(x) => write_ron_str(out, &x.1),
+        AstItem::ValueSocketAddr(x) => write_ron_str(out, &x.0),
+        AstItem::ValueStr(x) => write_ron_str(out, &x.unescape()),
+    }
+}
+
+/// Write the children of a value one per line, the way `ron::ser::PrettyConfig` does.
+fn write_ron_children<'x, 'a: 'x>(
+    out: &mut String,
+    open: &str,
+    children: impl IntoIterator<Item = RonChild<'x, 'a>>,
+    close: &str,
+    depth: usize,
+) {
+    const INDENT: &str = "    ";
+
+    out.push_str(open);
+    let mut is_empty = true;
+    for child in children {
+        is_empty = false;
+        out.push('\n');
+        out.push_str(&INDENT.repeat(depth + 1));
+        match child {
+            RonChild::Item(value) => write_ron(out, value, depth + 1),
+            RonChild::Field(name, value) => {
+                out.push_str(name);
+                out.push_str(": ");
+                write_ron(out, &value, depth + 1);
+            }
+            RonChild::Entry(key, value) => {
+                write_ron(out, key, depth + 1);
+                out.push_str(": ");
+                write_ron(out, value, depth + 1);
+            }
+        }
+        out.push(',');
+    }
+    if !is_empty {
+        out.push('\n');
+        out.push_str(&INDENT.repeat(depth));
+    }
+    out.push_str(close);
+}
+
+fn write_ron_str(out: &mut String, text: &str) {
+    // RON strings use the same escape sequences as Rust.
+    out.push_str(&format!("{text:?}"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse, parse_ron};
+
+    #[test]
+    fn json_table() {
+        let cases = [
+            ("Foo { a: 1, .. }", json!({"$type": "Foo", "a": 1})),
+            ("Foo(1)", json!({"Foo": 1})),
+            ("Foo(1, 2)", json!({"Foo": [1, 2]})),
+            ("Foo", json!("Foo")),
+            ("Some(1)", json!(1)),
+            ("None", json!(null)),
+            ("[1, 2]", json!([1, 2])),
+            ("{1, 2}", json!([1, 2])),
+            ("(1, 2)", json!([1, 2])),
+            (r#"{"a": 1}"#, json!({"a": 1})),
+            ("{1: 2}", json!([{"key": 1, "value": 2}])),
+            ("{}", json!({})),
+            (
+                "1..=2",
+                json!({"$type": "RangeInclusive", "start": 1, "end": 2}),
+            ),
+            ("..2", json!({"$type": "Range", "start": null, "end": 2})),
+            ("1.5s", json!({"secs": 1, "nanos": 500_000_000})),
+            (r#"b"ab""#, json!([97, 98])),
+            (r#""a\n""#, json!("a\n")),
+            (r#"r"a\n""#, json!("a\\n")),
+            ("-3", json!(-3)),
+            ("18446744073709551615", json!(u64::MAX)),
+            (
+                "340282366920938463463374607431768211455",
+                json!("340282366920938463463374607431768211455"),
+            ),
+            ("2.5", json!(2.5)),
+            ("1e20", json!(1e20)),
+            ("-1.5e-7", json!(-1.5e-7)),
+            ("-inf", json!("-inf")),
+            ("{ a: 1 }", json!({"a": 1})),
+            ("127.0.0.1:80", json!("127.0.0.1:80")),
+            ("0x7ffd", json!("0x7ffd")),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(to_value(&parse(text).unwrap()), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn ron_table() {
+        let cases = [
+            ("Foo { a: 1, .. }", "Foo(\n    a: 1,\n)"),
+            ("Foo(1)", "Foo(\n    1,\n)"),
+            ("Foo", "Foo"),
+            ("Some([])", "Some([])"),
+            ("None", "None"),
+            ("{1, 2}", "[\n    1,\n    2,\n]"),
+            ("(1, 2)", "(\n    1,\n    2,\n)"),
+            ("{1: 2}", "{\n    1: 2,\n}"),
+            ("{}", "{}"),
+            ("1..", "Range(\n    start: 1,\n    end: None,\n)"),
+            ("1.5s", "(\n    secs: 1,\n    nanos: 500000000,\n)"),
+            (r#"b"ab""#, "[\n    97,\n    98,\n]"),
+            (r#"r"a\""#, r#""a\\""#),
+            ("-inf", "-inf"),
+            ("[::1]:80", r#""[::1]:80""#),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(to_ron(&parse(text).unwrap()), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn ron_parses_back() {
+        let text = r#"Foo { a: Some([1, 2]), b: Bar(1..=3, "x\n"), c: {"k": Baz { d: true }} }"#;
+        let ron = to_ron(&parse(text).unwrap());
+
+        assert!(ron::from_str::<ron::Value>(&ron).is_ok(), "{ron}");
+        assert_eq!(
+            parse_ron(&ron).unwrap(),
+            parse(
+                r#"Foo {
+                    a: Some([1, 2]),
+                    b: Bar(RangeInclusive { start: 1, end: 3 }, "x\n"),
+                    c: {"k": Baz { d: true }},
+                }"#
+            )
+            .unwrap()
+        );
+    }
+}
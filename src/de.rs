@@ -0,0 +1,526 @@
+//! Conversion of a tree back into any value implementing `Deserialize`.
+//!
+//! This is the reverse of [`ser`](crate::ser), so that values printed using `Debug` can be loaded
+//! into the types which printed them:
+//!
+//! | Tree                                 | Converted into                         |
+//! |--------------------------------------|----------------------------------------|
+//! | `ItemStruct`, `ItemMap`, `ItemEmpty` | structs and maps                       |
+//! | `ItemStruct("V", ..)`                | `E::V { .. }` when an enum is expected |
+//! | `ItemTupleStruct("V", ..)`           | `E::V(..)` when an enum is expected    |
+//! | `ItemUnitStruct("V")`                | `E::V` when an enum is expected        |
+//! | `ItemTupleStruct("Foo", ..)`         | newtypes and tuple structs             |
+//! | `ItemOption`                         | `Option`                               |
+//! | `ItemList`, `ItemSet`, `ItemTuple`   | sequences, sets and tuples             |
+//! | `ItemRange`                          | `Range`, `RangeInclusive`              |
+//! | `ValueNum`, `ValueBool`              | numbers, booleans                      |
+//! | `ItemUnitStruct("NaN")`, `("inf")`   | `f32`, `f64` when a float is expected  |
+//! | `ValueStr`, addresses, opaque values | strings (and anything parsed from one) |
+//! | `ValueDuration`                      | `Duration`                             |
+//! | `ValueBytes`                         | byte buffers                           |
+//!
+//! Struct names are ignored unless an enum variant is expected, and so are the hidden fields of
+//! non-exhaustive structs (`Foo { a: 1, .. }`), which deserialize like missing fields.
+
+use crate::ast::*;
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use std::{error, fmt, vec};
+
+/// Convert a tree into a value, as described above.
+pub fn from_ast<T: DeserializeOwned>(item: &AstItem) -> Result<T, Error> {
+    T::deserialize(Deserializer(item))
+}
+
+/// Why a tree couldn't be converted into a value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// A `Deserializer` reading a tree.
+///
+/// Strings are never borrowed from the tree, since most of them have to be unescaped.
+#[derive(Clone, Copy)]
+pub struct Deserializer<'b, 'a>(pub &'b AstItem<'a>);
+
+impl<'de, 'b, 'a> de::Deserializer<'de> for Deserializer<'b, 'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            AstItem::ItemEmpty(_) => visitor.visit_map(MapAccess::new(Vec::new())),
+            AstItem::ItemList(x) => visitor.visit_seq(SeqAccess::new(x.0.iter().collect())),
+            AstItem::ItemMap(x) => visitor.visit_map(MapAccess::new(
+                x.0.iter().map(|(k, v)| (Key::Item(k), v)).collect(),
+            )),
+            AstItem::ItemOption(x) => match x.0.as_deref() {
+                Some(x) => visitor.visit_some(Deserializer(x)),
+                None => visitor.visit_none(),
+            },
+            AstItem::ItemRange(x) => {
+                let bounds = [("start", &x.0), ("end", &x.2)];
+                visitor.visit_map(MapAccess::new(
+                    bounds
+                        .into_iter()
+                        .filter_map(|(k, v)| Some((Key::Name(k), v.as_deref()?)))
+                        .collect(),
+                ))
+            }
+            AstItem::ItemSet(x) => visitor.visit_seq(SeqAccess::new(x.0.iter().collect())),
+            AstItem::ItemStruct(x) => visitor.visit_map(MapAccess::new(
+                x.1.iter().map(|(k, v)| (Key::Name(k), v)).collect(),
+            )),
+            AstItem::ItemTuple(x) if x.0.is_empty() => visitor.visit_unit(),
+            AstItem::ItemTuple(x) => visitor.visit_seq(SeqAccess::new(x.0.iter().collect())),
+            AstItem::ItemTupleStruct(x) => visitor.visit_seq(SeqAccess::new(x.1.iter().collect())),
+            AstItem::ItemUnitStruct(x) => visitor.visit_str(&x.0),
+            AstItem::ValueBool(x) => visitor.visit_bool(x.0),
+            AstItem::ValueBytes(x) => visitor.visit_byte_buf(x.to_bytes()),
+            AstItem::ValueDuration(x) => {
+                let duration = x
+                    .to_duration()
+                    .ok_or_else(|| Error(format!("invalid duration `{x}`")))?;
+                let secs = ValueNum(duration.as_secs().to_string().into()).into();
+                let nanos = ValueNum(duration.subsec_nanos().to_string().into()).into();
+                visitor.visit_map(MapAccess::new(vec![
+                    (Key::Name("secs"), &secs),
+                    (Key::Name("nanos"), &nanos),
+                ]))
+            }
+            AstItem::ValueIpAddr(x) => visitor.visit_str(&x.0),
+            AstItem::ValueNum(x) => visit_num(&x.0, visitor),
+            AstItem::ValueOpaque(x) => visitor.visit_str(&x.1),
+            AstItem::ValueSocketAddr(x) => visitor.visit_str(&x.0),
+            AstItem::ValueStr(x) => visitor.visit_str(&x.unescape()),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            // `Debug` prints these the way it would print unit structs.
+            AstItem::ItemUnitStruct(x) if x.0 == "NaN" => visitor.visit_f64(f64::NAN),
+            AstItem::ItemUnitStruct(x) if x.0 == "inf" => visitor.visit_f64(f64::INFINITY),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            AstItem::ItemOption(x) => match x.0.as_deref() {
+                Some(x) => visitor.visit_some(Deserializer(x)),
+                None => visitor.visit_none(),
+            },
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            AstItem::ItemUnitStruct(_) => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            AstItem::ItemTupleStruct(x) if x.1.len() == 1 => {
+                visitor.visit_newtype_struct(Deserializer(&x.1[0]))
+            }
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            // `{}` may also be an empty set.
+            AstItem::ItemEmpty(_) => visitor.visit_seq(SeqAccess::new(Vec::new())),
+            AstItem::ItemTuple(x) => visitor.visit_seq(SeqAccess::new(x.0.iter().collect())),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            AstItem::ItemStruct(ItemStruct(name, ..))
+            | AstItem::ItemTupleStruct(ItemTupleStruct(name, _))
+            | AstItem::ItemUnitStruct(ItemUnitStruct(name)) => {
+                visitor.visit_enum(EnumAccess { name, item: self.0 })
+            }
+            AstItem::ValueStr(x) => {
+                visitor.visit_enum(x.unescape().into_owned().into_deserializer())
+            }
+            _ => Err(Error(format!(
+                "expected an enum variant, found `{}`",
+                self.0
+            ))),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf unit
+        map struct identifier
+    }
+}
+
+/// Visit a number with the narrowest type which holds it.
+fn visit_num<'de, V: Visitor<'de>>(text: &str, visitor: V) -> Result<V::Value, Error> {
+    let error = || Error(format!("invalid number `{text}`"));
+    if let Ok(value) = text.parse::<u64>() {
+        visitor.visit_u64(value)
+    } else if let Ok(value) = text.parse::<i64>() {
+        visitor.visit_i64(value)
+    } else if let Ok(value) = text.parse::<u128>() {
+        visitor.visit_u128(value)
+    } else if let Ok(value) = text.parse::<i128>() {
+        visitor.visit_i128(value)
+    } else {
+        visitor.visit_f64(text.parse().map_err(|_| error())?)
+    }
+}
+
+struct SeqAccess<'b, 'a>(vec::IntoIter<&'b AstItem<'a>>);
+
+impl<'b, 'a> SeqAccess<'b, 'a> {
+    fn new(items: Vec<&'b AstItem<'a>>) -> Self {
+        Self(items.into_iter())
+    }
+}
+
+impl<'de, 'b, 'a> de::SeqAccess<'de> for SeqAccess<'b, 'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.0
+            .next()
+            .map(|x| seed.deserialize(Deserializer(x)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+/// The key of a map entry: either a field name or any value.
+enum Key<'b, 'a> {
+    Name(&'b str),
+    Item(&'b AstItem<'a>),
+}
+
+struct MapAccess<'b, 'a> {
+    entries: vec::IntoIter<(Key<'b, 'a>, &'b AstItem<'a>)>,
+    /// The value of the last key.
+    value: Option<&'b AstItem<'a>>,
+}
+
+impl<'b, 'a> MapAccess<'b, 'a> {
+    fn new(entries: Vec<(Key<'b, 'a>, &'b AstItem<'a>)>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de, 'b, 'a> de::MapAccess<'de> for MapAccess<'b, 'a> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        self.value = Some(value);
+        match key {
+            Key::Name(x) => seed.deserialize(x.into_deserializer()).map(Some),
+            Key::Item(x) => seed.deserialize(Deserializer(x)).map(Some),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error("map value without a key".to_owned()))?;
+        seed.deserialize(Deserializer(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// A struct, tuple struct or unit struct read as the enum variant it's named after.
+struct EnumAccess<'b, 'a> {
+    name: &'b str,
+    item: &'b AstItem<'a>,
+}
+
+impl<'de, 'b, 'a> de::EnumAccess<'de> for EnumAccess<'b, 'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.name.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'b, 'a> de::VariantAccess<'de> for EnumAccess<'b, 'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.item {
+            AstItem::ItemUnitStruct(_) => Ok(()),
+            _ => Err(self.mismatch("a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.item {
+            AstItem::ItemTupleStruct(x) if x.1.len() == 1 => {
+                seed.deserialize(Deserializer(&x.1[0]))
+            }
+            _ => Err(self.mismatch("a newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.item {
+            AstItem::ItemTupleStruct(x) => visitor.visit_seq(SeqAccess::new(x.1.iter().collect())),
+            _ => Err(self.mismatch("a tuple variant")),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.item {
+            AstItem::ItemStruct(_) => {
+                de::Deserializer::deserialize_any(Deserializer(self.item), visitor)
+            }
+            _ => Err(self.mismatch("a struct variant")),
+        }
+    }
+}
+
+impl<'b, 'a> EnumAccess<'b, 'a> {
+    fn mismatch(&self, expected: &str) -> Error {
+        Error(format!("expected {expected}, found `{}`", self.item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, ser::to_ast};
+    use serde::{Deserialize, Serialize};
+    use std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
+        fmt::Debug,
+        time::Duration,
+    };
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct User {
+        name: String,
+        age: u8,
+        tags: BTreeSet<String>,
+        manager: Option<Box<User>>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect(u32, u32),
+        Named { name: String, sides: u8 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Id(u32);
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Marker;
+
+    fn load<T: DeserializeOwned>(text: &str) -> T {
+        from_ast(&parse(text).unwrap()).unwrap()
+    }
+
+    /// Check that a value is read back the same from its `Debug` output and from its serialized
+    /// tree.
+    fn round_trip<T: Debug + DeserializeOwned + PartialEq + Serialize>(value: T) {
+        assert_eq!(load::<T>(&format!("{value:?}")), value);
+        assert_eq!(from_ast::<T>(&to_ast(&value).unwrap()).unwrap(), value);
+    }
+
+    #[test]
+    fn structs() {
+        round_trip(User {
+            name: "Ann \"A\"\n".to_owned(),
+            age: 42,
+            tags: ["admin".to_owned()].into(),
+            manager: Some(Box::new(User {
+                name: "Bob".to_owned(),
+                age: 50,
+                tags: BTreeSet::new(),
+                manager: None,
+            })),
+        });
+        round_trip(Id(7));
+        round_trip(Marker);
+        round_trip((1u8, "a".to_owned(), ()));
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Partial {
+            a: Option<u8>,
+            b: u8,
+        }
+        // Hidden fields are missing, and the name doesn't matter.
+        assert_eq!(
+            load::<Partial>("Other { b: 1, .. }"),
+            Partial { a: None, b: 1 }
+        );
+    }
+
+    #[test]
+    fn enums() {
+        round_trip(Shape::Empty);
+        round_trip(Shape::Circle(1.5));
+        round_trip(Shape::Rect(2, 3));
+        round_trip(Shape::Named {
+            name: "tri".to_owned(),
+            sides: 3,
+        });
+        round_trip(vec![Shape::Empty, Shape::Rect(0, 0)]);
+        assert_eq!(load::<Shape>(r#""Empty""#), Shape::Empty);
+
+        let error = from_ast::<Shape>(&parse("Rect { a: 1 }").unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a tuple variant, found `Rect { a: 1 }`"
+        );
+    }
+
+    #[test]
+    fn maps() {
+        round_trip(BTreeMap::from([
+            (1u32, "a".to_owned()),
+            (20, "b".to_owned()),
+        ]));
+        round_trip(BTreeMap::from([((1u8, -2i8), true)]));
+        round_trip(BTreeMap::from([(Some("x".to_owned()), vec![1u8])]));
+        round_trip(HashMap::<String, u8>::new());
+        round_trip(BTreeSet::<u8>::new());
+    }
+
+    #[test]
+    fn options() {
+        round_trip(Some(1u8));
+        round_trip(None::<u8>);
+        round_trip(Some(None::<u8>));
+        // The `Some` may be left out.
+        assert_eq!(load::<Option<u8>>("1"), Some(1));
+    }
+
+    #[test]
+    fn wide_integers() {
+        round_trip(u64::MAX);
+        round_trip(i64::MIN);
+        round_trip(u128::MAX);
+        round_trip(i128::MIN);
+        round_trip(vec![i128::MAX, 0, -1]);
+        assert!(from_ast::<u8>(&parse("256").unwrap()).is_err());
+    }
+
+    #[test]
+    fn floats() {
+        round_trip(vec![0.1, -1.5e-9, 1e20, f64::INFINITY, f64::NEG_INFINITY]);
+        round_trip(vec![0.1f32, 3e38]);
+        assert!(load::<f64>("NaN").is_nan());
+        assert!(from_ast::<f64>(&to_ast(&f64::NAN).unwrap())
+            .unwrap()
+            .is_nan());
+        assert_eq!(load::<f32>("inf"), f32::INFINITY);
+        // Elsewhere, they're still unit structs.
+        assert_eq!(load::<String>("NaN"), "NaN");
+    }
+
+    #[test]
+    fn durations() {
+        round_trip(Duration::from_millis(1500));
+        round_trip(Duration::from_nanos(7));
+        round_trip(Duration::from_secs(90061));
+    }
+}
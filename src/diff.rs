@@ -1,5 +1,14 @@
-use crate::ast::*;
-use std::cmp::Ordering;
+use crate::{ast::*, path::PathPattern};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    mem,
+    time::Duration,
+};
+
+static EMPTY_MAP: ItemMap<'static> = ItemMap(BTreeMap::new());
+static EMPTY_SET: ItemSet<'static> = ItemSet(BTreeSet::new());
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct DiffItem<'a, 'b> {
@@ -7,67 +16,394 @@ pub struct DiffItem<'a, 'b> {
     pub diff: DiffPayload<'a, 'b>,
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+impl<'a, 'b> DiffItem<'a, 'b> {
+    /// Copy the borrowed parts of both trees, so that the difference no longer borrows them and can
+    /// be stored or sent to another thread.
+    pub fn into_owned(self) -> DiffItem<'static, 'static> {
+        DiffItem {
+            path: self
+                .path
+                .into_iter()
+                .map(DiffPathSegment::into_owned)
+                .collect(),
+            diff: self.diff.into_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
 pub enum DiffPathSegment<'a, 'b> {
-    Field(&'a str),
+    Field(Cow<'b, str>),
     Index(usize, usize),
-    Key(&'b AstItem<'a>),
+    Key(Cow<'b, AstItem<'a>>),
+    /// An item of a list identified by the value of one of its fields, as described by
+    /// [`DiffConfig::list_keys`].
+    KeyField(Cow<'b, str>, Cow<'b, AstItem<'a>>),
+}
+
+impl<'a, 'b> DiffPathSegment<'a, 'b> {
+    /// See [`DiffItem::into_owned`].
+    pub fn into_owned(self) -> DiffPathSegment<'static, 'static> {
+        match self {
+            DiffPathSegment::Field(x) => DiffPathSegment::Field(Cow::Owned(x.into_owned())),
+            DiffPathSegment::Index(l, r) => DiffPathSegment::Index(l, r),
+            DiffPathSegment::Key(x) => {
+                DiffPathSegment::Key(Cow::Owned(x.into_owned().into_owned()))
+            }
+            DiffPathSegment::KeyField(k, x) => DiffPathSegment::KeyField(
+                Cow::Owned(k.into_owned()),
+                Cow::Owned(x.into_owned().into_owned()),
+            ),
+        }
+    }
 }
 
 pub type DiffPath<'a, 'b> = Vec<DiffPathSegment<'a, 'b>>;
 
+/// What differs, along with the value on each side (or on the only one that has it).
+///
+/// The values are borrowed from the trees being compared, unless [`DiffItem::into_owned`] was
+/// called.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
 pub enum DiffPayload<'a, 'b> {
-    AstItem(&'b AstItem<'a>, &'b AstItem<'a>),
+    AstItem(Cow<'b, AstItem<'a>>, Cow<'b, AstItem<'a>>),
     // ...
-    ItemStruct(&'b ItemStruct<'a>, &'b ItemStruct<'a>),
-    ItemTuple(&'b ItemTuple<'a>, &'b ItemTuple<'a>),
-    ItemTupleStruct(&'b ItemTupleStruct<'a>, &'b ItemTupleStruct<'a>),
-    ItemUnitStruct(&'b ItemUnitStruct<'a>, &'b ItemUnitStruct<'a>),
-    ValueBool(&'b ValueBool, &'b ValueBool),
-    ValueNum(&'b ValueNum<'a>, &'b ValueNum<'a>),
-    ValueStr(&'b ValueStr<'a>, &'b ValueStr<'a>),
+    ItemRange(Cow<'b, ItemRange<'a>>, Cow<'b, ItemRange<'a>>),
+    ItemStruct(Cow<'b, ItemStruct<'a>>, Cow<'b, ItemStruct<'a>>),
+    ItemTuple(Cow<'b, ItemTuple<'a>>, Cow<'b, ItemTuple<'a>>),
+    ItemTupleStruct(Cow<'b, ItemTupleStruct<'a>>, Cow<'b, ItemTupleStruct<'a>>),
+    ItemUnitStruct(Cow<'b, ItemUnitStruct<'a>>, Cow<'b, ItemUnitStruct<'a>>),
+    ValueBool(Cow<'b, ValueBool>, Cow<'b, ValueBool>),
+    ValueBytes(Cow<'b, ValueBytes<'a>>, Cow<'b, ValueBytes<'a>>),
+    ValueDuration(Cow<'b, ValueDuration<'a>>, Cow<'b, ValueDuration<'a>>),
+    ValueIpAddr(Cow<'b, ValueIpAddr<'a>>, Cow<'b, ValueIpAddr<'a>>),
+    ValueNum(Cow<'b, ValueNum<'a>>, Cow<'b, ValueNum<'a>>),
+    ValueOpaque(Cow<'b, ValueOpaque<'a>>, Cow<'b, ValueOpaque<'a>>),
+    ValueSocketAddr(Cow<'b, ValueSocketAddr<'a>>, Cow<'b, ValueSocketAddr<'a>>),
+    ValueStr(Cow<'b, ValueStr<'a>>, Cow<'b, ValueStr<'a>>),
 
     /// The value was removed (not present on the right).
-    InsertedAt(usize, &'b AstItem<'a>),
+    InsertedAt(usize, Cow<'b, AstItem<'a>>),
     /// The value was inserted (not present on the left).
-    RemovedAt(usize, &'b AstItem<'a>),
+    RemovedAt(usize, Cow<'b, AstItem<'a>>),
     /// The value was removed (not present on the right).
-    InsertedPair(&'b AstItem<'a>, &'b AstItem<'a>),
+    InsertedPair(Cow<'b, AstItem<'a>>, Cow<'b, AstItem<'a>>),
     /// The value was inserted (not present on the left).
-    RemovedPair(&'b AstItem<'a>, &'b AstItem<'a>),
+    RemovedPair(Cow<'b, AstItem<'a>>, Cow<'b, AstItem<'a>>),
+    /// The value appears a different number of times on each side of an unordered list.
+    CountChanged(Cow<'b, AstItem<'a>>, usize, usize),
+    /// The key of a list identified by a field is held by several items, on the left and the right.
+    DuplicateKey(usize, usize),
+}
+
+impl<'a, 'b> DiffPayload<'a, 'b> {
+    /// See [`DiffItem::into_owned`].
+    pub fn into_owned(self) -> DiffPayload<'static, 'static> {
+        // Both the reference into the tree and the text within it have to be copied.
+        macro_rules! owned {
+            ($x:expr) => {
+                Cow::Owned($x.into_owned().into_owned())
+            };
+        }
+
+        match self {
+            DiffPayload::AstItem(l, r) => DiffPayload::AstItem(owned!(l), owned!(r)),
+            DiffPayload::ItemRange(l, r) => DiffPayload::ItemRange(owned!(l), owned!(r)),
+            DiffPayload::ItemStruct(l, r) => DiffPayload::ItemStruct(owned!(l), owned!(r)),
+            DiffPayload::ItemTuple(l, r) => DiffPayload::ItemTuple(owned!(l), owned!(r)),
+            DiffPayload::ItemTupleStruct(l, r) => {
+                DiffPayload::ItemTupleStruct(owned!(l), owned!(r))
+            }
+            DiffPayload::ItemUnitStruct(l, r) => DiffPayload::ItemUnitStruct(owned!(l), owned!(r)),
+            DiffPayload::ValueBytes(l, r) => DiffPayload::ValueBytes(owned!(l), owned!(r)),
+            DiffPayload::ValueDuration(l, r) => DiffPayload::ValueDuration(owned!(l), owned!(r)),
+            DiffPayload::ValueIpAddr(l, r) => DiffPayload::ValueIpAddr(owned!(l), owned!(r)),
+            DiffPayload::ValueNum(l, r) => DiffPayload::ValueNum(owned!(l), owned!(r)),
+            DiffPayload::ValueOpaque(l, r) => DiffPayload::ValueOpaque(owned!(l), owned!(r)),
+            DiffPayload::ValueSocketAddr(l, r) => {
+                DiffPayload::ValueSocketAddr(owned!(l), owned!(r))
+            }
+            DiffPayload::ValueStr(l, r) => DiffPayload::ValueStr(owned!(l), owned!(r)),
+            DiffPayload::ValueBool(l, r) => {
+                DiffPayload::ValueBool(Cow::Owned(l.into_owned()), Cow::Owned(r.into_owned()))
+            }
+            DiffPayload::InsertedAt(i, r) => DiffPayload::InsertedAt(i, owned!(r)),
+            DiffPayload::RemovedAt(i, l) => DiffPayload::RemovedAt(i, owned!(l)),
+            DiffPayload::InsertedPair(k, r) => DiffPayload::InsertedPair(owned!(k), owned!(r)),
+            DiffPayload::RemovedPair(k, l) => DiffPayload::RemovedPair(owned!(k), owned!(l)),
+            DiffPayload::CountChanged(x, l, r) => DiffPayload::CountChanged(owned!(x), l, r),
+            DiffPayload::DuplicateKey(l, r) => DiffPayload::DuplicateKey(l, r),
+        }
+    }
+}
+
+/// Options that change which differences are reported.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct DiffConfig {
+    /// Compare opaque values (pointers, `<locked>`, closures...) by their text.
+    ///
+    /// By default two opaque values of the same kind are always considered equal, since things like
+    /// addresses never match across runs.
+    pub strict_opaque: bool,
+    /// Compare values with a known meaning (durations, addresses...) by what they represent rather
+    /// than by their text, so that `1000ms` equals `1s` and `::1` equals `0:0:0:0:0:0:0:1`.
+    pub semantic: bool,
+    /// How far apart two durations may be while still being considered equal.
+    pub duration_tolerance: Option<Duration>,
+    /// Consider values that other formats (JSON, RON) can't write the way `Debug` does as equal to
+    /// their closest equivalent:
+    ///
+    /// - `Some(x)` equals `x`, since JSON has no way to tell them apart.
+    /// - Lists, tuples and sets with the same items are equal (ignoring the order if either one is a
+    ///   set), and `{}` equals an empty list.
+    /// - A unit struct equals a string holding its name, and a tuple struct `Foo(x)` equals the
+    ///   object `{"Foo": x}`, which is how `serde` writes enum variants.
+    /// - A map whose keys are strings equals an object with the same fields.
+    /// - Addresses, durations and opaque values equal a string with the same text.
+    /// - Numbers are compared by value, so that `2` equals `2.0` and `0x1F` equals `31`.
+    ///
+    /// Structs without a name (JSON objects, anonymous RON structs) are always compared by their
+    /// fields only, whatever the name of the other struct.
+    pub cross_format: bool,
+    /// Don't compare the values whose path matches one of these patterns, nor anything within them.
+    ///
+    /// Map entries, struct fields and items of lists identified by a field (see
+    /// [`list_keys`](Self::list_keys)) which only one side has are ignored too when their path
+    /// matches. Lists holding some of these values are then aligned and compared item by item, like
+    /// [`find_diff_seq`] does.
+    pub ignore: Vec<PathPattern>,
+    /// Compare the lists whose path matches one of these patterns as multisets, ignoring the order
+    /// of their items.
+    ///
+    /// Equal items are paired first. Values which appear a different number of times on each side
+    /// are then reported once with both counts, and the remaining items as removed or inserted.
+    pub unordered: Vec<PathPattern>,
+    /// Identify the items of the lists whose path matches a pattern by one of their fields, the way
+    /// a map identifies its values by their key.
+    ///
+    /// Items are paired with the one holding the same value in that field on the other side,
+    /// wherever it is, and the path to their differences is written `[id=42]`. Lists holding an
+    /// item which isn't a struct with that field are compared by position instead. A key held by
+    /// several items on either side is reported with both counts, and these items are then paired
+    /// in order.
+    pub list_keys: Vec<(PathPattern, String)>,
+}
+
+pub fn find_diff<'a, 'b>(
+    lhs: &'b AstItem<'a>,
+    rhs: &'b AstItem<'a>,
+    config: &DiffConfig,
+) -> Vec<DiffItem<'a, 'b>> {
+    let mut target = Vec::new();
+    let mut stack = Vec::new();
+
+    diff_ast(config, &mut target, &mut stack, lhs, rhs);
+
+    target
 }
 
-pub fn find_diff<'a, 'b>(lhs: &'b AstItem<'a>, rhs: &'b AstItem<'a>) -> Vec<DiffItem<'a, 'b>> {
+/// Find the differences between two values found at the same path within larger trees.
+///
+/// Both the reported paths and the patterns of the configuration are then relative to the roots of
+/// those trees rather than to the values.
+pub fn find_diff_at<'a, 'b>(
+    path: DiffPath<'a, 'b>,
+    lhs: &'b AstItem<'a>,
+    rhs: &'b AstItem<'a>,
+    config: &DiffConfig,
+) -> Vec<DiffItem<'a, 'b>> {
+    let mut target = Vec::new();
+    let mut stack = path;
+
+    diff_ast(config, &mut target, &mut stack, lhs, rhs);
+
+    target
+}
+
+/// Find the differences between two sequences of values.
+///
+/// The sequences are aligned first, so that inserting or removing a value doesn't cause every
+/// following one to be reported as different. Values between two aligned ones are compared in
+/// order, and the path of their differences starts with an index into each sequence.
+pub fn find_diff_seq<'a, 'b>(
+    lhs: &'b [AstItem<'a>],
+    rhs: &'b [AstItem<'a>],
+    config: &DiffConfig,
+) -> Vec<DiffItem<'a, 'b>> {
     let mut target = Vec::new();
     let mut stack = Vec::new();
 
-    diff_ast(&mut target, &mut stack, lhs, rhs);
+    diff_seq(config, &mut target, &mut stack, lhs, rhs);
 
     target
 }
 
+/// Compare two sequences of values, as described by [`find_diff_seq`].
+fn diff_seq<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b [AstItem<'a>],
+    rhs: &'b [AstItem<'a>],
+) {
+    let anchors = align(lhs.len(), rhs.len(), |l, r| {
+        let segment = DiffPathSegment::Index(l, r);
+        lhs[l] == rhs[r] || is_equal_at(config, stack, segment, &lhs[l], &rhs[r])
+    });
+
+    let (mut lhs_pos, mut rhs_pos) = (0, 0);
+    for (lhs_end, rhs_end) in anchors.into_iter().chain([(lhs.len(), rhs.len())]) {
+        // Values between two aligned ones are paired by their shape, so that an inserted value
+        // doesn't get compared against a changed one.
+        let pairs = align(lhs_end - lhs_pos, rhs_end - rhs_pos, |l, r| {
+            is_same_shape(&lhs[lhs_pos + l], &rhs[rhs_pos + r])
+        });
+        let (mut lhs_next, mut rhs_next) = (lhs_pos, rhs_pos);
+        for (lhs_pair, rhs_pair) in pairs
+            .into_iter()
+            .map(|(l, r)| (lhs_pos + l, rhs_pos + r))
+            .chain([(lhs_end, rhs_end)])
+        {
+            for (lhs_idx, lhs) in lhs.iter().enumerate().take(lhs_pair).skip(lhs_next) {
+                target.push(DiffItem {
+                    path: stack.clone(),
+                    diff: DiffPayload::RemovedAt(lhs_idx, Cow::Borrowed(lhs)),
+                });
+            }
+            for (rhs_idx, rhs) in rhs.iter().enumerate().take(rhs_pair).skip(rhs_next) {
+                target.push(DiffItem {
+                    path: stack.clone(),
+                    diff: DiffPayload::InsertedAt(rhs_idx, Cow::Borrowed(rhs)),
+                });
+            }
+
+            if lhs_pair < lhs_end {
+                stack.push(DiffPathSegment::Index(lhs_pair, rhs_pair));
+                diff_ast(config, target, stack, &lhs[lhs_pair], &rhs[rhs_pair]);
+                stack.pop();
+            }
+
+            (lhs_next, rhs_next) = (lhs_pair + 1, rhs_pair + 1);
+        }
+
+        (lhs_pos, rhs_pos) = (lhs_end + 1, rhs_end + 1);
+    }
+}
+
+/// Whether two values have no differences once the segment is added to the path.
+fn is_equal_at<'a, 'b>(
+    config: &DiffConfig,
+    stack: &mut DiffPath<'a, 'b>,
+    segment: DiffPathSegment<'a, 'b>,
+    lhs: &'b AstItem<'a>,
+    rhs: &'b AstItem<'a>,
+) -> bool {
+    let mut target = Vec::new();
+    stack.push(segment);
+    diff_ast(config, &mut target, stack, lhs, rhs);
+    stack.pop();
+
+    target.is_empty()
+}
+
+/// Whether both items are the same kind of value (and have the same name, if they have one).
+fn is_same_shape(lhs: &AstItem, rhs: &AstItem) -> bool {
+    match (lhs, rhs) {
+        (AstItem::ItemStruct(lhs), AstItem::ItemStruct(rhs)) => lhs.0 == rhs.0,
+        (AstItem::ItemTupleStruct(lhs), AstItem::ItemTupleStruct(rhs)) => lhs.0 == rhs.0,
+        (AstItem::ItemUnitStruct(lhs), AstItem::ItemUnitStruct(rhs)) => lhs.0 == rhs.0,
+        _ => mem::discriminant(lhs) == mem::discriminant(rhs),
+    }
+}
+
+/// Find the longest common subsequence of both sides, returning the indices of the matched pairs.
+fn align(
+    lhs_len: usize,
+    rhs_len: usize,
+    mut eq: impl FnMut(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+    let is_equal = (0..lhs_len)
+        .map(|l| (0..rhs_len).map(|r| eq(l, r)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut lengths = vec![vec![0usize; rhs_len + 1]; lhs_len + 1];
+    for i in (0..lhs_len).rev() {
+        for j in (0..rhs_len).rev() {
+            lengths[i][j] = if is_equal[i][j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < lhs_len && j < rhs_len {
+        if is_equal[i][j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    pairs
+}
+
 fn diff_ast<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b AstItem<'a>,
     rhs: &'b AstItem<'a>,
 ) {
+    if is_ignored(config, stack) {
+        return;
+    }
+    if let (AstItem::ItemList(lhs), AstItem::ItemList(rhs)) = (lhs, rhs) {
+        if let Some(field) = key_field(config, stack, lhs, rhs) {
+            return diff_item_keyed(config, target, stack, field, lhs, rhs);
+        }
+    }
+
     match (lhs, rhs) {
-        (AstItem::ItemList(lhs), AstItem::ItemList(rhs)) => diff_item_list(target, stack, lhs, rhs),
-        (AstItem::ItemMap(lhs), AstItem::ItemMap(rhs)) => diff_item_map(target, stack, lhs, rhs),
+        (AstItem::ItemList(lhs), AstItem::ItemList(rhs)) if is_unordered(config, stack) => {
+            diff_item_multiset(config, target, stack, &lhs.0, &rhs.0)
+        }
+        // Items are compared as a whole, unless something within them may be ignored.
+        (AstItem::ItemList(lhs), AstItem::ItemList(rhs)) if is_ignored_within(config, stack) => {
+            diff_seq(config, target, stack, &lhs.0, &rhs.0)
+        }
+        (AstItem::ItemList(lhs), AstItem::ItemList(rhs)) => {
+            diff_item_list(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ItemMap(lhs), AstItem::ItemMap(rhs)) => {
+            diff_item_map(config, target, stack, lhs, rhs)
+        }
         (AstItem::ItemOption(lhs), AstItem::ItemOption(rhs)) => {
-            diff_item_option(target, stack, lhs, rhs)
+            diff_item_option(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ItemRange(lhs), AstItem::ItemRange(rhs)) => {
+            diff_item_range(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ItemSet(lhs), AstItem::ItemSet(rhs)) => {
+            diff_item_set(config, target, stack, lhs, rhs)
         }
-        (AstItem::ItemSet(lhs), AstItem::ItemSet(rhs)) => diff_item_set(target, stack, lhs, rhs),
         (AstItem::ItemStruct(lhs), AstItem::ItemStruct(rhs)) => {
-            diff_item_struct(target, stack, lhs, rhs)
+            diff_item_struct(config, target, stack, lhs, rhs)
         }
         (AstItem::ItemTuple(lhs), AstItem::ItemTuple(rhs)) => {
-            diff_item_tuple(target, stack, lhs, rhs)
+            diff_item_tuple(config, target, stack, lhs, rhs)
         }
         (AstItem::ItemTupleStruct(lhs), AstItem::ItemTupleStruct(rhs)) => {
-            diff_item_tuple_struct(target, stack, lhs, rhs)
+            diff_item_tuple_struct(config, target, stack, lhs, rhs)
         }
         (AstItem::ItemUnitStruct(lhs), AstItem::ItemUnitStruct(rhs)) => {
             diff_item_unit_struct(target, stack, lhs, rhs)
@@ -75,58 +411,309 @@ fn diff_ast<'a, 'b>(
         (AstItem::ValueBool(lhs), AstItem::ValueBool(rhs)) => {
             diff_value_bool(target, stack, lhs, rhs)
         }
-        (AstItem::ValueNum(lhs), AstItem::ValueNum(rhs)) => diff_value_num(target, stack, lhs, rhs),
+        (AstItem::ValueBytes(lhs), AstItem::ValueBytes(rhs)) => {
+            diff_value_bytes(target, stack, lhs, rhs)
+        }
+        (AstItem::ValueDuration(lhs), AstItem::ValueDuration(rhs)) => {
+            diff_value_duration(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ValueIpAddr(lhs), AstItem::ValueIpAddr(rhs)) => {
+            diff_value_ip_addr(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ValueNum(lhs), AstItem::ValueNum(rhs)) => {
+            diff_value_num(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ValueOpaque(lhs), AstItem::ValueOpaque(rhs)) => {
+            diff_value_opaque(config, target, stack, lhs, rhs)
+        }
+        (AstItem::ValueSocketAddr(lhs), AstItem::ValueSocketAddr(rhs)) => {
+            diff_value_socket_addr(config, target, stack, lhs, rhs)
+        }
         (AstItem::ValueStr(lhs), AstItem::ValueStr(rhs)) => diff_value_str(target, stack, lhs, rhs),
+        (AstItem::ItemEmpty(_), AstItem::ItemEmpty(_)) => {}
+        (lhs, AstItem::ItemMap(rhs)) if is_empty_braces(lhs) => {
+            diff_item_map(config, target, stack, &EMPTY_MAP, rhs)
+        }
+        (AstItem::ItemMap(lhs), rhs) if is_empty_braces(rhs) => {
+            diff_item_map(config, target, stack, lhs, &EMPTY_MAP)
+        }
+        (lhs, AstItem::ItemSet(rhs)) if is_empty_braces(lhs) => {
+            diff_item_set(config, target, stack, &EMPTY_SET, rhs)
+        }
+        (AstItem::ItemSet(lhs), rhs) if is_empty_braces(rhs) => {
+            diff_item_set(config, target, stack, lhs, &EMPTY_SET)
+        }
+        _ if config.cross_format => diff_cross_format(config, target, stack, lhs, rhs),
         _ => target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::AstItem(lhs, rhs),
+            diff: DiffPayload::AstItem(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         }),
     }
 }
 
+/// Compare two different kinds of values which may still be equivalent, as described by
+/// [`DiffConfig::cross_format`].
+fn diff_cross_format<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b AstItem<'a>,
+    rhs: &'b AstItem<'a>,
+) {
+    match (lhs, rhs) {
+        (AstItem::ItemOption(ItemOption(Some(lhs))), rhs) => {
+            return diff_ast(config, target, stack, lhs, rhs);
+        }
+        (lhs, AstItem::ItemOption(ItemOption(Some(rhs)))) => {
+            return diff_ast(config, target, stack, lhs, rhs);
+        }
+        _ => {}
+    }
+
+    let is_equal = if let (Some(l), Some(r)) = (as_items(lhs), as_items(rhs)) {
+        return diff_items(config, target, stack, (lhs, rhs), l, r);
+    } else if let (Some(l), Some(r)) = (as_text(lhs), as_text(rhs)) {
+        l == r
+    } else {
+        let pairs = match (lhs, rhs) {
+            (AstItem::ItemStruct(l), _) if l.0.is_empty() => pair_fields(config, stack, l, rhs),
+            (_, AstItem::ItemStruct(r)) if r.0.is_empty() => pair_fields(config, stack, r, lhs)
+                .map(|x| x.into_iter().map(|(k, r, l)| (k, l, r)).collect()),
+            _ => None,
+        };
+
+        match pairs {
+            Some(pairs) => {
+                for (k, lhs, rhs) in pairs {
+                    stack.push(DiffPathSegment::Field(Cow::Borrowed(k)));
+                    diff_ast(config, target, stack, lhs, rhs);
+                    stack.pop();
+                }
+                true
+            }
+            None => false,
+        }
+    };
+
+    if !is_equal {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::AstItem(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+/// The items of a sequence, along with whether their order is irrelevant.
+fn as_items<'a, 'b>(item: &'b AstItem<'a>) -> Option<(Vec<&'b AstItem<'a>>, bool)> {
+    match item {
+        AstItem::ItemEmpty(_) => Some((Vec::new(), false)),
+        AstItem::ItemList(x) => Some((x.0.iter().collect(), false)),
+        AstItem::ItemSet(x) => Some((x.0.iter().collect(), true)),
+        AstItem::ItemStruct(x) if x.0.is_empty() && x.1.is_empty() => Some((Vec::new(), false)),
+        AstItem::ItemTuple(x) => Some((x.0.iter().collect(), false)),
+        _ => None,
+    }
+}
+
+/// The text of a value which other formats can only write as a string.
+fn as_text<'b>(item: &'b AstItem) -> Option<Cow<'b, str>> {
+    match item {
+        AstItem::ItemUnitStruct(x) => Some(Cow::Borrowed(&x.0)),
+        AstItem::ValueDuration(x) => Some(Cow::Borrowed(&x.0)),
+        AstItem::ValueIpAddr(x) => Some(Cow::Borrowed(&x.0)),
+        AstItem::ValueOpaque(x) => Some(Cow::Borrowed(&x.1)),
+        AstItem::ValueSocketAddr(x) => Some(Cow::Borrowed(&x.0)),
+        AstItem::ValueStr(x) => Some(x.unescape()),
+        _ => None,
+    }
+}
+
+/// Pair the fields of a struct without a name with the entries of a map or the value of a newtype,
+/// returning the name of each field along with its value on both sides.
+///
+/// Like for two structs, both sides must hold the same fields, apart from those which are ignored
+/// or hidden behind `..`.
+fn pair_fields<'a, 'b>(
+    config: &DiffConfig,
+    stack: &mut DiffPath<'a, 'b>,
+    fields: &'b ItemStruct<'a>,
+    other: &'b AstItem<'a>,
+) -> Option<Vec<(&'b str, &'b AstItem<'a>, &'b AstItem<'a>)>> {
+    let entries = match other {
+        AstItem::ItemMap(x) => {
+            x.0.iter()
+                .map(|(k, v)| match k {
+                    AstItem::ValueStr(k) => Some((k.unescape(), v)),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?
+        }
+        AstItem::ItemTupleStruct(x) if x.1.len() == 1 => {
+            vec![(Cow::Borrowed(x.0.as_ref()), &x.1[0])]
+        }
+        _ => return None,
+    };
+
+    let mut pairs = Vec::with_capacity(entries.len());
+    for (k, other) in entries {
+        match fields.1.get_key_value(k.as_ref()) {
+            // `"a"` and `r"a"` are different keys of a map, but the same field.
+            Some((k, _)) if pairs.iter().any(|&(x, _, _)| x == k) => return None,
+            Some((k, field)) => pairs.push((k.as_ref(), field, other)),
+            None => {
+                let segment = DiffPathSegment::Field(Cow::Owned(k.into_owned()));
+                if !is_child_ignored(config, stack, segment) {
+                    return None;
+                }
+            }
+        }
+    }
+
+    // Fields hidden behind `..` can't be compared, so only the visible ones must match.
+    if !fields.2 {
+        for k in fields.1.keys() {
+            let is_paired = pairs.iter().any(|&(x, _, _)| x == k);
+            let segment = DiffPathSegment::Field(Cow::Borrowed(k));
+            if !is_paired && !is_child_ignored(config, stack, segment) {
+                return None;
+            }
+        }
+    }
+
+    Some(pairs)
+}
+
+/// Compare the items of two different kinds of sequences.
+fn diff_items<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    (lhs, rhs): (&'b AstItem<'a>, &'b AstItem<'a>),
+    (mut lhs_items, lhs_unordered): (Vec<&'b AstItem<'a>>, bool),
+    (mut rhs_items, rhs_unordered): (Vec<&'b AstItem<'a>>, bool),
+) {
+    if lhs_unordered || rhs_unordered {
+        for items in [&mut lhs_items, &mut rhs_items] {
+            items.sort();
+            items.dedup();
+        }
+        diff_sorted_items(
+            config,
+            target,
+            stack,
+            lhs_items.into_iter(),
+            rhs_items.into_iter(),
+        );
+    } else if lhs_items.len() == rhs_items.len() {
+        for (idx, (lhs, rhs)) in lhs_items.into_iter().zip(rhs_items).enumerate() {
+            stack.push(DiffPathSegment::Index(idx, idx));
+            diff_ast(config, target, stack, lhs, rhs);
+            stack.pop();
+        }
+    } else {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::AstItem(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+/// Whether the value at this path mustn't be compared, as described by [`DiffConfig::ignore`].
+fn is_ignored(config: &DiffConfig, stack: &DiffPath) -> bool {
+    config.ignore.iter().any(|x| x.matches(stack))
+}
+
+/// Whether the list at this path is compared as a multiset, as described by
+/// [`DiffConfig::unordered`].
+fn is_unordered(config: &DiffConfig, stack: &DiffPath) -> bool {
+    config.unordered.iter().any(|x| x.matches(stack))
+}
+
+/// Whether some value within the one at this path mustn't be compared.
+fn is_ignored_within(config: &DiffConfig, stack: &DiffPath) -> bool {
+    config.ignore.iter().any(|x| x.matches_within(stack))
+}
+
+/// Whether the child of the value at this path mustn't be compared.
+fn is_child_ignored<'a, 'b>(
+    config: &DiffConfig,
+    stack: &mut DiffPath<'a, 'b>,
+    segment: DiffPathSegment<'a, 'b>,
+) -> bool {
+    if config.ignore.is_empty() {
+        return false;
+    }
+
+    stack.push(segment);
+    let ignored = is_ignored(config, stack);
+    stack.pop();
+
+    ignored
+}
+
+/// Whether the item would be formatted as `{}`, which may be either an empty map or an empty set.
+fn is_empty_braces(item: &AstItem) -> bool {
+    match item {
+        AstItem::ItemEmpty(_) => true,
+        AstItem::ItemMap(x) => x.0.is_empty(),
+        AstItem::ItemSet(x) => x.0.is_empty(),
+        _ => false,
+    }
+}
+
 fn diff_item_list<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemList<'a>,
     rhs: &'b ItemList<'a>,
 ) {
-    let mut lhs_iter = lhs.0.iter().peekable();
-    let mut rhs_iter = rhs.0.iter().peekable();
+    let mut lhs_iter = lhs.0.iter().enumerate().peekable();
+    let mut rhs_iter = rhs.0.iter().enumerate().peekable();
 
     let mut count = 0;
     loop {
-        match (lhs_iter.peek(), rhs_iter.peek()) {
-            (Some(lhs), Some(rhs)) => match lhs.cmp(rhs) {
-                Ordering::Equal => {
-                    lhs_iter.next();
-                    rhs_iter.next();
-                }
-                Ordering::Less => {
-                    target.push(DiffItem {
-                        path: stack.clone(),
-                        diff: DiffPayload::RemovedAt(count, lhs),
-                    });
-                    lhs_iter.next();
-                }
-                Ordering::Greater => {
-                    target.push(DiffItem {
-                        path: stack.clone(),
-                        diff: DiffPayload::RemovedAt(count, rhs),
-                    });
-                    rhs_iter.next();
+        match (lhs_iter.peek().copied(), rhs_iter.peek().copied()) {
+            (Some((lhs_idx, lhs)), Some((rhs_idx, rhs))) => {
+                let segment = DiffPathSegment::Index(lhs_idx, rhs_idx);
+                let ordering = if lhs == rhs || is_equal_at(config, stack, segment, lhs, rhs) {
+                    Ordering::Equal
+                } else {
+                    lhs.cmp(rhs)
+                };
+
+                match ordering {
+                    Ordering::Equal => {
+                        lhs_iter.next();
+                        rhs_iter.next();
+                    }
+                    Ordering::Less => {
+                        target.push(DiffItem {
+                            path: stack.clone(),
+                            diff: DiffPayload::RemovedAt(count, Cow::Borrowed(lhs)),
+                        });
+                        lhs_iter.next();
+                    }
+                    Ordering::Greater => {
+                        target.push(DiffItem {
+                            path: stack.clone(),
+                            diff: DiffPayload::InsertedAt(count, Cow::Borrowed(rhs)),
+                        });
+                        rhs_iter.next();
+                    }
                 }
-            },
-            (Some(lhs), None) => {
+            }
+            (Some((_, lhs)), None) => {
                 target.push(DiffItem {
                     path: stack.clone(),
-                    diff: DiffPayload::RemovedAt(count, lhs),
+                    diff: DiffPayload::RemovedAt(count, Cow::Borrowed(lhs)),
                 });
                 lhs_iter.next();
             }
-            (None, Some(rhs)) => {
+            (None, Some((_, rhs))) => {
                 target.push(DiffItem {
                     path: stack.clone(),
-                    diff: DiffPayload::RemovedAt(count, rhs),
+                    diff: DiffPayload::InsertedAt(count, Cow::Borrowed(rhs)),
                 });
                 rhs_iter.next();
             }
@@ -137,7 +724,264 @@ fn diff_item_list<'a, 'b>(
     }
 }
 
+/// The field identifying the items of the lists at this path, as described by
+/// [`DiffConfig::list_keys`], if every item has it.
+fn key_field<'c>(
+    config: &'c DiffConfig,
+    stack: &DiffPath,
+    lhs: &ItemList,
+    rhs: &ItemList,
+) -> Option<&'c str> {
+    let (_, field) = config.list_keys.iter().find(|(x, _)| x.matches(stack))?;
+    lhs.0
+        .iter()
+        .chain(&rhs.0)
+        .all(|x| list_item_key(x, field).is_some())
+        .then_some(field.as_str())
+}
+
+/// The name of the field identifying an item of a list, along with its value.
+fn list_item_key<'a, 'b>(item: &'b AstItem<'a>, field: &str) -> Option<(&'b str, &'b AstItem<'a>)> {
+    match item {
+        AstItem::ItemStruct(x) => x.1.get_key_value(field).map(|(k, v)| (k.as_ref(), v)),
+        _ => None,
+    }
+}
+
+/// Compare two lists whose items are identified by a field, as described by
+/// [`DiffConfig::list_keys`].
+fn diff_item_keyed<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    field: &str,
+    lhs: &'b ItemList<'a>,
+    rhs: &'b ItemList<'a>,
+) {
+    // `key_field` made sure that every item has a key.
+    let keys = |list: &'b ItemList<'a>| {
+        list.0
+            .iter()
+            .map(|x| list_item_key(x, field))
+            .collect::<Option<Vec<_>>>()
+    };
+    let (Some(lhs_keys), Some(rhs_keys)) = (keys(lhs), keys(rhs)) else {
+        return diff_item_list(config, target, stack, lhs, rhs);
+    };
+
+    // The position of the items holding each key, on both sides.
+    let mut indices = HashMap::<_, (Vec<usize>, Vec<usize>)>::new();
+    for (l, &(_, key)) in lhs_keys.iter().enumerate() {
+        indices.entry(key).or_default().0.push(l);
+    }
+    for (r, &(_, key)) in rhs_keys.iter().enumerate() {
+        indices.entry(key).or_default().1.push(r);
+    }
+
+    // A key doesn't identify the items sharing it: it's reported once, and these items are then
+    // paired in order, the first one on each side together and so on.
+    let mut duplicates = HashSet::new();
+    for &(name, key) in lhs_keys.iter().chain(&rhs_keys) {
+        let (lhs_count, rhs_count) = (indices[key].0.len(), indices[key].1.len());
+        if (lhs_count > 1 || rhs_count > 1) && duplicates.insert(key) {
+            stack.push(DiffPathSegment::KeyField(
+                Cow::Borrowed(name),
+                Cow::Borrowed(key),
+            ));
+            if !is_ignored(config, stack) {
+                target.push(DiffItem {
+                    path: stack.clone(),
+                    diff: DiffPayload::DuplicateKey(lhs_count, rhs_count),
+                });
+            }
+            stack.pop();
+        }
+    }
+
+    let mut rhs_paired = vec![false; rhs.0.len()];
+    for (l, lhs) in lhs.0.iter().enumerate() {
+        let (name, key) = lhs_keys[l];
+        let segment = DiffPathSegment::KeyField(Cow::Borrowed(name), Cow::Borrowed(key));
+        let (lhs_indices, rhs_indices) = &indices[key];
+        let nth = lhs_indices.partition_point(|&x| x < l);
+        match rhs_indices.get(nth) {
+            Some(&r) => {
+                rhs_paired[r] = true;
+                stack.push(segment);
+                diff_ast(config, target, stack, lhs, &rhs.0[r]);
+                stack.pop();
+            }
+            None if is_child_ignored(config, stack, segment) => {}
+            None => target.push(DiffItem {
+                path: stack.clone(),
+                diff: DiffPayload::RemovedAt(l, Cow::Borrowed(lhs)),
+            }),
+        }
+    }
+    for (r, rhs) in rhs.0.iter().enumerate().filter(|&(r, _)| !rhs_paired[r]) {
+        let (name, key) = rhs_keys[r];
+        let segment = DiffPathSegment::KeyField(Cow::Borrowed(name), Cow::Borrowed(key));
+        if !is_child_ignored(config, stack, segment) {
+            target.push(DiffItem {
+                path: stack.clone(),
+                diff: DiffPayload::InsertedAt(r, Cow::Borrowed(rhs)),
+            });
+        }
+    }
+}
+
+/// Pair as many items as possible with an equal one on the other side, returning the item on the
+/// left paired with each one on the right.
+///
+/// Pairing each item with the first equal one isn't enough once equality isn't transitive: if `a`
+/// equals both `x` and `y` but `b` only `x`, then `a` has to give `x` up to `b`. This is a maximum
+/// bipartite matching, grown one item at a time by moving the items already paired along an
+/// augmenting path.
+fn pair_equal(is_equal: &[Vec<bool>], rhs_len: usize) -> Vec<Option<usize>> {
+    let mut pairs = vec![None; rhs_len];
+    for l in 0..is_equal.len() {
+        let mut visited = vec![false; rhs_len];
+        // The path is searched depth first, with an explicit stack since it may be as long as the
+        // lists. Each frame is an item on the left along with the next item on the right it should
+        // try, the one before being the one the path goes through.
+        let mut stack = vec![(l, 0)];
+        while let Some(frame) = stack.last_mut() {
+            let (l, from) = *frame;
+            let Some(r) = (from..rhs_len).find(|&r| is_equal[l][r] && !visited[r]) else {
+                stack.pop();
+                continue;
+            };
+            frame.1 = r + 1;
+            visited[r] = true;
+
+            match pairs[r] {
+                Some(other) => stack.push((other, 0)),
+                None => {
+                    for &(l, next) in &stack {
+                        pairs[next - 1] = Some(l);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// Compare two lists ignoring the order of their items, as described by [`DiffConfig::unordered`].
+fn diff_item_multiset<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b [AstItem<'a>],
+    rhs: &'b [AstItem<'a>],
+) {
+    let is_equal = (0..lhs.len())
+        .map(|l| {
+            (0..rhs.len())
+                .map(|r| {
+                    let segment = DiffPathSegment::Index(l, r);
+                    lhs[l] == rhs[r] || is_equal_at(config, stack, segment, &lhs[l], &rhs[r])
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let pairs = pair_equal(&is_equal, rhs.len());
+    let rhs_paired = pairs.iter().map(Option::is_some).collect::<Vec<_>>();
+    let lhs_unpaired = (0..lhs.len()).filter(|l| !pairs.contains(&Some(*l)));
+
+    // An item left over while the other side has equal ones appears a different number of times
+    // on each side, which is reported once per value. Equal items are never left over on both
+    // sides, since they would make the pairing larger. With a tolerance, equality isn't transitive
+    // and the counts are those of the values equal to the first pair found.
+    let count = |l: usize, r: usize| {
+        let lhs_count = (0..lhs.len()).filter(|&i| is_equal[i][r]).count();
+        let rhs_count = (0..rhs.len()).filter(|&j| is_equal[l][j]).count();
+        (lhs_count, rhs_count)
+    };
+    let (mut lhs_left, mut rhs_left) = (Vec::new(), Vec::new());
+    let mut counted = Vec::new();
+    for l in lhs_unpaired {
+        let diff = match (0..rhs.len()).find(|&r| is_equal[l][r]) {
+            Some(r) if counted.contains(&r) => continue,
+            Some(r) => {
+                counted.push(r);
+                let (lhs_count, rhs_count) = count(l, r);
+                DiffPayload::CountChanged(Cow::Borrowed(&lhs[l]), lhs_count, rhs_count)
+            }
+            None => {
+                lhs_left.push(l);
+                continue;
+            }
+        };
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff,
+        });
+    }
+    let mut counted = Vec::new();
+    for r in (0..rhs.len()).filter(|&r| !rhs_paired[r]) {
+        let diff = match (0..lhs.len()).find(|&l| is_equal[l][r]) {
+            Some(l) if counted.contains(&l) => continue,
+            Some(l) => {
+                counted.push(l);
+                let (lhs_count, rhs_count) = count(l, r);
+                DiffPayload::CountChanged(Cow::Borrowed(&rhs[r]), lhs_count, rhs_count)
+            }
+            None => {
+                rhs_left.push(r);
+                continue;
+            }
+        };
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff,
+        });
+    }
+
+    // The remaining values with children are paired by their shape and compared, so that changing
+    // a field of an item doesn't report it as both removed and inserted.
+    for l in lhs_left {
+        let pair = rhs_left
+            .iter()
+            .position(|&r| has_children(&lhs[l]) && is_same_shape(&lhs[l], &rhs[r]));
+        match pair {
+            Some(pos) => {
+                let r = rhs_left.remove(pos);
+                stack.push(DiffPathSegment::Index(l, r));
+                diff_ast(config, target, stack, &lhs[l], &rhs[r]);
+                stack.pop();
+            }
+            None => target.push(DiffItem {
+                path: stack.clone(),
+                diff: DiffPayload::RemovedAt(l, Cow::Borrowed(&lhs[l])),
+            }),
+        }
+    }
+    for r in rhs_left {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::InsertedAt(r, Cow::Borrowed(&rhs[r])),
+        });
+    }
+}
+
+/// Whether the item holds other values, rather than being a single one.
+fn has_children(item: &AstItem) -> bool {
+    matches!(
+        item,
+        AstItem::ItemList(_)
+            | AstItem::ItemMap(_)
+            | AstItem::ItemSet(_)
+            | AstItem::ItemStruct(_)
+            | AstItem::ItemTuple(_)
+            | AstItem::ItemTupleStruct(_)
+    )
+}
+
 fn diff_item_map<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemMap<'a>,
@@ -148,44 +992,37 @@ fn diff_item_map<'a, 'b>(
 
     loop {
         match (lhs_iter.peek(), rhs_iter.peek()) {
-            (Some((lhs_key, lhs_val)), Some((rhs_key, rhs_val))) => {
-                match lhs_key.cmp(rhs_key) {
-                    Ordering::Equal => {
-                        stack.push(DiffPathSegment::Key(lhs_key));
-                        diff_ast(target, stack, lhs_val, rhs_val);
-                        stack.pop();
+            (Some((lhs_key, lhs_val)), Some((rhs_key, rhs_val))) => match lhs_key.cmp(rhs_key) {
+                Ordering::Equal => {
+                    stack.push(DiffPathSegment::Key(Cow::Borrowed(lhs_key)));
+                    diff_ast(config, target, stack, lhs_val, rhs_val);
+                    stack.pop();
 
-                        lhs_iter.next();
-                        rhs_iter.next();
-                    }
-                    Ordering::Less => {
-                        target.push(DiffItem {
-                            path: stack.clone(),
-                            diff: DiffPayload::RemovedPair(lhs_key, lhs_val),
-                        });
-                        lhs_iter.next();
-                    }
-                    Ordering::Greater => {
-                        target.push(DiffItem {
-                            path: stack.clone(),
-                            diff: DiffPayload::InsertedPair(rhs_key, rhs_val),
-                        });
-                        rhs_iter.next();
-                    }
+                    lhs_iter.next();
+                    rhs_iter.next();
+                }
+                Ordering::Less => {
+                    let diff =
+                        DiffPayload::RemovedPair(Cow::Borrowed(lhs_key), Cow::Borrowed(lhs_val));
+                    push_map_entry(config, target, stack, lhs_key, diff);
+                    lhs_iter.next();
+                }
+                Ordering::Greater => {
+                    let diff =
+                        DiffPayload::InsertedPair(Cow::Borrowed(rhs_key), Cow::Borrowed(rhs_val));
+                    push_map_entry(config, target, stack, rhs_key, diff);
+                    rhs_iter.next();
                 }
             },
             (Some((lhs_key, lhs_val)), None) => {
-                target.push(DiffItem {
-                    path: stack.clone(),
-                    diff: DiffPayload::RemovedPair(lhs_key, lhs_val),
-                });
+                let diff = DiffPayload::RemovedPair(Cow::Borrowed(lhs_key), Cow::Borrowed(lhs_val));
+                push_map_entry(config, target, stack, lhs_key, diff);
                 lhs_iter.next();
             }
             (None, Some((rhs_key, rhs_val))) => {
-                target.push(DiffItem {
-                    path: stack.clone(),
-                    diff: DiffPayload::InsertedPair(rhs_key, rhs_val),
-                });
+                let diff =
+                    DiffPayload::InsertedPair(Cow::Borrowed(rhs_key), Cow::Borrowed(rhs_val));
+                push_map_entry(config, target, stack, rhs_key, diff);
                 rhs_iter.next();
             }
             (None, None) => break,
@@ -193,35 +1030,90 @@ fn diff_item_map<'a, 'b>(
     }
 }
 
+/// Report an entry which only one of the maps has, unless its path is ignored.
+fn push_map_entry<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    key: &'b AstItem<'a>,
+    diff: DiffPayload<'a, 'b>,
+) {
+    if !is_child_ignored(config, stack, DiffPathSegment::Key(Cow::Borrowed(key))) {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff,
+        });
+    }
+}
+
 fn diff_item_option<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemOption<'a>,
     rhs: &'b ItemOption<'a>,
 ) {
     match (lhs.0.as_deref(), rhs.0.as_deref()) {
-        (Some(lhs), Some(rhs)) => diff_ast(target, stack, lhs, rhs),
+        (Some(lhs), Some(rhs)) => diff_ast(config, target, stack, lhs, rhs),
         (Some(lhs), None) => target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::RemovedAt(0, lhs),
+            diff: DiffPayload::RemovedAt(0, Cow::Borrowed(lhs)),
         }),
         (None, Some(rhs)) => target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::InsertedAt(0, rhs),
+            diff: DiffPayload::InsertedAt(0, Cow::Borrowed(rhs)),
         }),
         (None, None) => {}
     }
 }
 
+fn diff_item_range<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b ItemRange<'a>,
+    rhs: &'b ItemRange<'a>,
+) {
+    if lhs.1 != rhs.1 || lhs.0.is_some() != rhs.0.is_some() || lhs.2.is_some() != rhs.2.is_some() {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::ItemRange(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    } else {
+        for (name, lhs, rhs) in [("start", &lhs.0, &rhs.0), ("end", &lhs.2, &rhs.2)] {
+            if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+                stack.push(DiffPathSegment::Field(Cow::Borrowed(name)));
+                diff_ast(config, target, stack, lhs, rhs);
+                stack.pop();
+            }
+        }
+    }
+}
+
 fn diff_item_set<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemSet<'a>,
     rhs: &'b ItemSet<'a>,
 ) {
-    let mut lhs_iter = lhs.0.iter().peekable();
-    let mut rhs_iter = rhs.0.iter().peekable();
+    diff_sorted_items(config, target, stack, lhs.0.iter(), rhs.0.iter());
+}
 
+/// Compare two sorted sequences of items without duplicates.
+fn diff_sorted_items<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: impl Iterator<Item = &'b AstItem<'a>>,
+    rhs: impl Iterator<Item = &'b AstItem<'a>>,
+) where
+    'a: 'b,
+{
+    let mut lhs_iter = lhs.peekable();
+    let mut rhs_iter = rhs.peekable();
+
+    let (mut removed, mut inserted) = (Vec::new(), Vec::new());
     let mut count = 0;
     loop {
         match (lhs_iter.peek(), rhs_iter.peek()) {
@@ -231,32 +1123,20 @@ fn diff_item_set<'a, 'b>(
                     rhs_iter.next();
                 }
                 Ordering::Less => {
-                    target.push(DiffItem {
-                        path: stack.clone(),
-                        diff: DiffPayload::RemovedAt(count, lhs),
-                    });
+                    removed.push((count, *lhs));
                     lhs_iter.next();
                 }
                 Ordering::Greater => {
-                    target.push(DiffItem {
-                        path: stack.clone(),
-                        diff: DiffPayload::InsertedAt(count, rhs),
-                    });
+                    inserted.push((count, *rhs));
                     rhs_iter.next();
                 }
             },
             (Some(lhs), None) => {
-                target.push(DiffItem {
-                    path: stack.clone(),
-                    diff: DiffPayload::RemovedAt(count, lhs),
-                });
+                removed.push((count, *lhs));
                 lhs_iter.next();
             }
             (None, Some(rhs)) => {
-                target.push(DiffItem {
-                    path: stack.clone(),
-                    diff: DiffPayload::InsertedAt(count, rhs),
-                });
+                inserted.push((count, *rhs));
                 rhs_iter.next();
             }
             (None, None) => break,
@@ -264,29 +1144,74 @@ fn diff_item_set<'a, 'b>(
 
         count += 1;
     }
+
+    // Items which are equal for the configuration but not in their text (`1000ms` and `1s`) may
+    // sort apart, so they're only paired now.
+    removed.retain(|&(lhs_idx, lhs)| {
+        let pair = inserted.iter().position(|&(rhs_idx, rhs)| {
+            is_equal_at(
+                config,
+                stack,
+                DiffPathSegment::Index(lhs_idx, rhs_idx),
+                lhs,
+                rhs,
+            )
+        });
+        pair.map(|x| inserted.remove(x)).is_none()
+    });
+
+    for (idx, lhs) in removed {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::RemovedAt(idx, Cow::Borrowed(lhs)),
+        });
+    }
+    for (idx, rhs) in inserted {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::InsertedAt(idx, Cow::Borrowed(rhs)),
+        });
+    }
 }
 
 fn diff_item_struct<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemStruct<'a>,
     rhs: &'b ItemStruct<'a>,
 ) {
-    if lhs.0 != rhs.0 || lhs.1.keys().zip(rhs.1.keys()).any(|(a, b)| a != b) {
+    // Fields hidden behind `..` can't be compared, so only the visible ones must match.
+    let non_exhaustive = lhs.2 || rhs.2;
+    // Structs without a name (JSON objects) can't disagree on it.
+    let same_name = lhs.0 == rhs.0 || lhs.0.is_empty() || rhs.0.is_empty();
+    // Fields which are ignored may be missing from either side.
+    let mut visible_keys = |x: &'b ItemStruct<'a>| {
+        x.1.keys()
+            .filter(|&k| !is_child_ignored(config, stack, DiffPathSegment::Field(Cow::Borrowed(k))))
+            .collect::<Vec<_>>()
+    };
+    let same_fields = non_exhaustive || visible_keys(lhs) == visible_keys(rhs);
+    if !same_name || !same_fields {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ItemStruct(lhs, rhs),
+            diff: DiffPayload::ItemStruct(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     } else {
-        for ((k, lhs), (_, rhs)) in lhs.1.iter().zip(rhs.1.iter()) {
-            stack.push(DiffPathSegment::Field(k));
-            diff_ast(target, stack, lhs, rhs);
+        for (k, lhs, rhs) in lhs
+            .1
+            .iter()
+            .filter_map(|(k, l)| Some((k, l, rhs.1.get(k)?)))
+        {
+            stack.push(DiffPathSegment::Field(Cow::Borrowed(k)));
+            diff_ast(config, target, stack, lhs, rhs);
             stack.pop();
         }
     }
 }
 
 fn diff_item_tuple<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemTuple<'a>,
@@ -295,18 +1220,19 @@ fn diff_item_tuple<'a, 'b>(
     if lhs.0.len() != rhs.0.len() {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ItemTuple(lhs, rhs),
+            diff: DiffPayload::ItemTuple(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     } else {
         for (idx, (lhs, rhs)) in lhs.0.iter().zip(rhs.0.iter()).enumerate() {
             stack.push(DiffPathSegment::Index(idx, idx));
-            diff_ast(target, stack, lhs, rhs);
+            diff_ast(config, target, stack, lhs, rhs);
             stack.pop();
         }
     }
 }
 
 fn diff_item_tuple_struct<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ItemTupleStruct<'a>,
@@ -315,12 +1241,12 @@ fn diff_item_tuple_struct<'a, 'b>(
     if lhs.0 != rhs.0 || lhs.1.len() != rhs.1.len() {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ItemTupleStruct(lhs, rhs),
+            diff: DiffPayload::ItemTupleStruct(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     } else {
         for (idx, (lhs, rhs)) in lhs.1.iter().zip(rhs.1.iter()).enumerate() {
             stack.push(DiffPathSegment::Index(idx, idx));
-            diff_ast(target, stack, lhs, rhs);
+            diff_ast(config, target, stack, lhs, rhs);
             stack.pop();
         }
     }
@@ -335,7 +1261,7 @@ fn diff_item_unit_struct<'a, 'b>(
     if lhs.0 != rhs.0 {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ItemUnitStruct(lhs, rhs),
+            diff: DiffPayload::ItemUnitStruct(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     }
 }
@@ -349,21 +1275,117 @@ fn diff_value_bool<'a, 'b>(
     if lhs.0 != rhs.0 {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ValueBool(lhs, rhs),
+            diff: DiffPayload::ValueBool(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+fn diff_value_bytes<'a, 'b>(
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b ValueBytes<'a>,
+    rhs: &'b ValueBytes<'a>,
+) {
+    if lhs.to_bytes() != rhs.to_bytes() {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::ValueBytes(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+fn diff_value_duration<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b ValueDuration<'a>,
+    rhs: &'b ValueDuration<'a>,
+) {
+    let is_equal = match (lhs.to_duration(), rhs.to_duration()) {
+        (Some(l), Some(r)) if config.semantic || config.duration_tolerance.is_some() => {
+            l.abs_diff(r) <= config.duration_tolerance.unwrap_or_default()
+        }
+        _ => lhs.0 == rhs.0,
+    };
+
+    if !is_equal {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::ValueDuration(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+fn diff_value_ip_addr<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b ValueIpAddr<'a>,
+    rhs: &'b ValueIpAddr<'a>,
+) {
+    let is_equal = match (lhs.to_ip_addr(), rhs.to_ip_addr()) {
+        (Some(l), Some(r)) if config.semantic => l == r,
+        _ => lhs.0 == rhs.0,
+    };
+
+    if !is_equal {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::ValueIpAddr(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     }
 }
 
 fn diff_value_num<'a, 'b>(
+    config: &DiffConfig,
     target: &mut Vec<DiffItem<'a, 'b>>,
     stack: &mut DiffPath<'a, 'b>,
     lhs: &'b ValueNum<'a>,
     rhs: &'b ValueNum<'a>,
 ) {
-    if lhs.0 != rhs.0 {
+    let same_value = || match (lhs.to_i128(), rhs.to_i128()) {
+        (Some(lhs), Some(rhs)) => lhs == rhs,
+        _ => lhs.to_f64().is_some_and(|x| rhs.to_f64() == Some(x)),
+    };
+    if lhs.0 != rhs.0 && !(config.cross_format && same_value()) {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::ValueNum(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+fn diff_value_opaque<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b ValueOpaque<'a>,
+    rhs: &'b ValueOpaque<'a>,
+) {
+    if lhs.0 != rhs.0 || (config.strict_opaque && lhs.1 != rhs.1) {
+        target.push(DiffItem {
+            path: stack.clone(),
+            diff: DiffPayload::ValueOpaque(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
+        });
+    }
+}
+
+fn diff_value_socket_addr<'a, 'b>(
+    config: &DiffConfig,
+    target: &mut Vec<DiffItem<'a, 'b>>,
+    stack: &mut DiffPath<'a, 'b>,
+    lhs: &'b ValueSocketAddr<'a>,
+    rhs: &'b ValueSocketAddr<'a>,
+) {
+    let is_equal = match (lhs.to_socket_addr(), rhs.to_socket_addr()) {
+        (Some(l), Some(r)) if config.semantic => l == r,
+        _ => lhs.0 == rhs.0,
+    };
+
+    if !is_equal {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ValueNum(lhs, rhs),
+            diff: DiffPayload::ValueSocketAddr(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     }
 }
@@ -374,10 +1396,514 @@ fn diff_value_str<'a, 'b>(
     lhs: &'b ValueStr<'a>,
     rhs: &'b ValueStr<'a>,
 ) {
-    if lhs.0 != rhs.0 {
+    if lhs.unescape() != rhs.unescape() {
         target.push(DiffItem {
             path: stack.clone(),
-            diff: DiffPayload::ValueStr(lhs, rhs),
+            diff: DiffPayload::ValueStr(Cow::Borrowed(lhs), Cow::Borrowed(rhs)),
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parse,
+        render::{PathText, Report},
+    };
+
+    fn diff(lhs: &str, rhs: &str, config: &DiffConfig) -> Vec<DiffItem<'static, 'static>> {
+        let (lhs, rhs) = (parse(lhs).unwrap(), parse(rhs).unwrap());
+        find_diff(&lhs, &rhs, config)
+            .into_iter()
+            .map(DiffItem::into_owned)
+            .collect()
+    }
+
+    /// The path and kind of each difference, like `.orders[id=42].status ValueStr`.
+    fn summary(diffs: &[DiffItem]) -> Vec<String> {
+        diffs
+            .iter()
+            .map(|x| {
+                let kind = format!("{:?}", x.diff);
+                let kind = &kind[..kind.find('(').unwrap_or(kind.len())];
+                format!("{} {kind}", PathText(&x.path))
+            })
+            .collect()
+    }
+
+    fn keyed_by_id() -> DiffConfig {
+        DiffConfig {
+            list_keys: vec![(".orders".parse().unwrap(), "id".to_owned())],
+            ..DiffConfig::default()
+        }
+    }
+
+    fn unordered() -> DiffConfig {
+        DiffConfig {
+            unordered: vec![".".parse().unwrap()],
+            ..DiffConfig::default()
+        }
+    }
+
+    fn ignoring(patterns: &[&str]) -> DiffConfig {
+        DiffConfig {
+            ignore: patterns.iter().map(|x| x.parse().unwrap()).collect(),
+            ..DiffConfig::default()
+        }
+    }
+
+    #[test]
+    fn opaque_in_sequences() {
+        let strict = DiffConfig {
+            strict_opaque: true,
+            ..DiffConfig::default()
+        };
+        for (lhs, rhs) in [
+            ("[0x1, 0x2]", "[0x3, 0x4]"),
+            ("{0x1, 0x2}", "{0x3, 0x4}"),
+            ("[A { f: <locked> }]", "[A { f: <poisoned> }]"),
+        ] {
+            assert!(
+                diff(lhs, rhs, &DiffConfig::default()).is_empty(),
+                "{lhs} {rhs}"
+            );
+            assert!(!diff(lhs, rhs, &strict).is_empty(), "{lhs} {rhs}");
+        }
+    }
+
+    #[test]
+    fn semantic_in_sequences() {
+        let semantic = DiffConfig {
+            semantic: true,
+            ..DiffConfig::default()
+        };
+        for (lhs, rhs) in [
+            ("[1000ms]", "[1s]"),
+            ("{1000ms, 2s}", "{1s, 2000ms}"),
+            ("[::1]", "[0:0:0:0:0:0:0:1]"),
+            ("{[::1]:80}", "{[0:0:0:0:0:0:0:1]:80}"),
+        ] {
+            assert!(diff(lhs, rhs, &semantic).is_empty(), "{lhs} {rhs}");
+            assert!(
+                !diff(lhs, rhs, &DiffConfig::default()).is_empty(),
+                "{lhs} {rhs}"
+            );
+        }
+    }
+
+    #[test]
+    fn duration_tolerance_in_sequences() {
+        let tolerant = DiffConfig {
+            duration_tolerance: Some(Duration::from_millis(5)),
+            ..DiffConfig::default()
+        };
+        assert!(diff("[1.002s, 3ms]", "[1s, 1ms]", &tolerant).is_empty());
+        assert!(diff("{1.002s}", "{1s}", &tolerant).is_empty());
+        assert_eq!(diff("[1.01s]", "[1s]", &tolerant).len(), 2);
+    }
+
+    #[test]
+    fn list_items_removed_and_inserted() {
+        let diffs = diff("[1, 2]", "[1, 3]", &DiffConfig::default());
+        assert!(matches!(diffs[0].diff, DiffPayload::RemovedAt(_, ref x) if x.to_string() == "2"));
+        assert!(matches!(diffs[1].diff, DiffPayload::InsertedAt(_, ref x) if x.to_string() == "3"));
+    }
+
+    #[test]
+    fn ignore_fields() {
+        let config = ignoring(&[".created_*"]);
+        let (lhs, rhs) = (
+            "A { created_at: 1, created_by: 2, x: 3 }",
+            "A { created_at: 4, x: 3 }",
+        );
+        assert!(diff(lhs, rhs, &config).is_empty());
+        assert_eq!(diff(lhs, "A { x: 4 }", &config).len(), 1);
+
+        // Without the rule, the missing field makes the structs different.
+        assert_eq!(diff(lhs, rhs, &DiffConfig::default()).len(), 1);
+    }
+
+    #[test]
+    fn ignore_map_entries() {
+        let config = ignoring(&[r#"["x-*"]"#]);
+        let lhs = r#"{"host": "a", "x-id": 1, "x-trace": 2}"#;
+        assert!(diff(lhs, r#"{"host": "a", "x-id": 3}"#, &config).is_empty());
+
+        let diffs = diff(lhs, r#"{"host": "b"}"#, &config);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(
+            diffs[0].path,
+            vec![DiffPathSegment::Key(Cow::Owned(
+                parse(r#""host""#).unwrap()
+            ))]
+        );
+    }
+
+    #[test]
+    fn ignore_within_lists() {
+        let config = ignoring(&[".items[*].at"]);
+        let lhs = "A { items: [B { at: 1, v: 1 }, B { at: 2, v: 2 }] }";
+        assert!(diff(
+            lhs,
+            "A { items: [B { at: 3, v: 1 }, B { at: 4, v: 2 }] }",
+            &config
+        )
+        .is_empty());
+
+        let diffs = diff(
+            lhs,
+            "A { items: [B { at: 3, v: 1 }, B { at: 4, v: 5 }] }",
+            &config,
+        );
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(
+            diffs[0].path.last(),
+            Some(&DiffPathSegment::Field("v".into()))
+        );
+    }
+
+    #[test]
+    fn ignore_leaves_other_lists_alone() {
+        // Lists the patterns can't reach are compared the same way as without them.
+        let (lhs, rhs) = ("A { a: [1, 2, 3], b: 1 }", "A { a: [1, 3], b: 2 }");
+        assert_eq!(
+            diff(lhs, rhs, &ignoring(&[".b"])),
+            diff(
+                "A { a: [1, 2, 3] }",
+                "A { a: [1, 3] }",
+                &DiffConfig::default()
+            ),
+        );
+    }
+
+    #[test]
+    fn keyed_pairing() {
+        let lhs = r#"A { orders: [O { id: 41, status: "paid" }, O { id: 42, status: "new" }] }"#;
+        let rhs = r#"A { orders: [O { id: 42, status: "sent" }, O { id: 41, status: "paid" }] }"#;
+        let diffs = diff(lhs, rhs, &keyed_by_id());
+        assert_eq!(summary(&diffs), [".orders[id=42].status ValueStr"]);
+        assert!(Report(&diffs)
+            .to_string()
+            .contains(r#"expected "sent", but got "new" at .orders[id=42].status."#));
+
+        // By position, both records differ.
+        assert_eq!(diff(lhs, rhs, &DiffConfig::default()).len(), 4);
+    }
+
+    #[test]
+    fn keyed_removed_and_inserted() {
+        let diffs = diff(
+            "A { orders: [O { id: 1 }, O { id: 2 }] }",
+            "A { orders: [O { id: 3 }, O { id: 1 }] }",
+            &keyed_by_id(),
+        );
+        assert_eq!(summary(&diffs), [".orders RemovedAt", ".orders InsertedAt"]);
+        assert!(matches!(diffs[0].diff, DiffPayload::RemovedAt(1, _)));
+        assert!(matches!(diffs[1].diff, DiffPayload::InsertedAt(0, _)));
+    }
+
+    #[test]
+    fn keyed_duplicates() {
+        let diffs = diff(
+            "A { orders: [O { id: 1, v: 1 }, O { id: 1, v: 2 }, O { id: 2, v: 3 }] }",
+            "A { orders: [O { id: 1, v: 1 }, O { id: 2, v: 3 }] }",
+            &keyed_by_id(),
+        );
+        assert_eq!(
+            summary(&diffs),
+            [".orders[id=1] DuplicateKey", ".orders RemovedAt"]
+        );
+        assert!(matches!(diffs[0].diff, DiffPayload::DuplicateKey(2, 1)));
+        assert!(matches!(diffs[1].diff, DiffPayload::RemovedAt(1, _)));
+    }
+
+    #[test]
+    fn keyed_ignore() {
+        let config = DiffConfig {
+            ignore: vec![".orders[id=42].status".parse().unwrap()],
+            ..keyed_by_id()
+        };
+        let diffs = diff(
+            "A { orders: [O { id: 41, status: 1 }, O { id: 42, status: 1 }] }",
+            "A { orders: [O { id: 42, status: 2 }, O { id: 41, status: 2 }] }",
+            &config,
+        );
+        assert_eq!(summary(&diffs), [".orders[id=41].status ValueNum"]);
+
+        // Items only one side has are ignored too when their path matches.
+        let config = DiffConfig {
+            ignore: vec![
+                ".orders[id=43]".parse().unwrap(),
+                ".orders[id=44]".parse().unwrap(),
+            ],
+            ..keyed_by_id()
+        };
+        let diffs = diff(
+            "A { orders: [O { id: 41 }, O { id: 43 }] }",
+            "A { orders: [O { id: 44 }, O { id: 41 }, O { id: 45 }] }",
+            &config,
+        );
+        assert_eq!(summary(&diffs), [".orders InsertedAt"]);
+        assert!(matches!(diffs[0].diff, DiffPayload::InsertedAt(2, _)));
+    }
+
+    #[test]
+    fn keyed_many() {
+        let items = |ids: &mut dyn Iterator<Item = usize>| {
+            let items = ids.map(|x| format!("O {{ id: {x} }}")).collect::<Vec<_>>();
+            format!("A {{ orders: [{}] }}", items.join(", "))
+        };
+        let lhs = items(&mut (0..5_000));
+        let rhs = items(&mut (1..5_001).rev());
+        let diffs = diff(&lhs, &rhs, &keyed_by_id());
+        assert_eq!(summary(&diffs), [".orders RemovedAt", ".orders InsertedAt"]);
+    }
+
+    #[test]
+    fn unordered_duplicates() {
+        let config = unordered();
+        assert!(diff("[1, 2, 1]", "[1, 1, 2]", &config).is_empty());
+
+        let diffs = diff("[1, 2, 1]", "[2, 1]", &config);
+        assert_eq!(summary(&diffs), [". CountChanged"]);
+        assert!(
+            matches!(diffs[0].diff, DiffPayload::CountChanged(ref x, 2, 1) if x.to_string() == "1")
+        );
+
+        let diffs = diff("[3, 1]", "[1, 3, 3, 3, 2]", &config);
+        assert_eq!(summary(&diffs), [". CountChanged", ". InsertedAt"]);
+        assert!(
+            matches!(diffs[0].diff, DiffPayload::CountChanged(ref x, 1, 3) if x.to_string() == "3")
+        );
+        assert!(matches!(diffs[1].diff, DiffPayload::InsertedAt(4, _)));
+    }
+
+    #[test]
+    fn unordered_changed_records() {
+        let diffs = diff(
+            "[A { id: 1, v: 1 }, B(2), 3]",
+            "[4, B(2), A { id: 1, v: 5 }]",
+            &unordered(),
+        );
+        assert_eq!(
+            summary(&diffs),
+            ["[0->2].v ValueNum", ". RemovedAt", ". InsertedAt"]
+        );
+    }
+
+    #[test]
+    fn unordered_tolerance() {
+        // `1s` equals both `997ms` and `1.004s`, but `994ms` only `997ms`: pairing `1s` with the
+        // first equal item would leave `994ms` and `1.004s` over.
+        let config = DiffConfig {
+            duration_tolerance: Some(Duration::from_millis(5)),
+            ..unordered()
+        };
+        assert!(diff("[1s, 994ms]", "[997ms, 1.004s]", &config).is_empty());
+        assert!(diff("[994ms, 1s]", "[1.004s, 997ms]", &config).is_empty());
+    }
+
+    #[test]
+    fn empty_braces() {
+        let config = DiffConfig::default();
+        assert!(diff("A { m: {} }", "A { m: {} }", &config).is_empty());
+        assert_eq!(
+            summary(&diff("{}", r#"{"a": 1}"#, &config)),
+            [". InsertedPair"]
+        );
+        assert_eq!(
+            summary(&diff(r#"{"a": 1}"#, "{}", &config)),
+            [". RemovedPair"]
+        );
+        assert_eq!(
+            summary(&diff("{}", "{1, 2}", &config)),
+            [". InsertedAt", ". InsertedAt"]
+        );
+        assert_eq!(summary(&diff("{1}", "{}", &config)), [". RemovedAt"]);
+        // A struct is never a map.
+        assert_eq!(summary(&diff("{}", "Foo {}", &config)), [". AstItem"]);
+    }
+
+    #[test]
+    fn ranges() {
+        let config = DiffConfig::default();
+        assert!(diff("0..10", "0..10", &config).is_empty());
+        assert_eq!(summary(&diff("0..10", "0..12", &config)), [".end ValueNum"]);
+        assert_eq!(
+            summary(&diff("A { r: 1..=2 }", "A { r: 0..=3 }", &config)),
+            [".r.start ValueNum", ".r.end ValueNum"]
+        );
+        assert_eq!(summary(&diff("0..10", "0..=10", &config)), [". ItemRange"]);
+        assert_eq!(summary(&diff("0..", "0..10", &config)), [". ItemRange"]);
+    }
+
+    #[test]
+    fn byte_strings() {
+        let config = DiffConfig::default();
+        assert!(diff(r#"b"a\x00""#, r#"b"\x61\0""#, &config).is_empty());
+
+        let diffs = diff(r#"b"\x00abc""#, r#"b"\x00aXc!""#, &config);
+        assert_eq!(summary(&diffs), [". ValueBytes"]);
+        let report = Report(&diffs).to_string();
+        assert!(report.contains("expected 5 bytes, but got 4"), "{report}");
+        assert!(report.contains("First difference at offset 2:"), "{report}");
+        assert!(report.contains("Expected: 00 61 [58] 63 21"), "{report}");
+    }
+
+    #[test]
+    fn align_longest_common_subsequence() {
+        let align_str = |lhs: &str, rhs: &str| {
+            let (lhs, rhs) = (lhs.as_bytes(), rhs.as_bytes());
+            align(lhs.len(), rhs.len(), |l, r| lhs[l] == rhs[r])
+        };
+        assert_eq!(align_str("", "abc"), []);
+        assert_eq!(align_str("abc", "abc"), [(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(align_str("abcde", "axcye"), [(0, 0), (2, 2), (4, 4)]);
+        assert_eq!(align_str("abc", "cab"), [(0, 1), (1, 2)]);
+        // The first of several equally long alignments.
+        assert_eq!(align_str("aab", "ab"), [(0, 0), (2, 1)]);
+        assert_eq!(align_str("xyz", "abc"), []);
+    }
+
+    #[test]
+    fn sequences() {
+        let seq = |lhs: &str, rhs: &str| {
+            let parse = |x| {
+                crate::parse_document(x, false)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(x, _)| x)
+                    .collect::<Vec<_>>()
+            };
+            let (lhs, rhs) = (parse(lhs), parse(rhs));
+            let diffs = find_diff_seq(&lhs, &rhs, &DiffConfig::default());
+            summary(&diffs)
+        };
+        assert_eq!(
+            seq("A(1) B(2) C(3)", "A(1) B(2) C(3)"),
+            Vec::<String>::new()
+        );
+        // An inserted value shifts the others, which are still compared with their counterpart.
+        assert_eq!(
+            seq("A(1) B(2) C(3)", "A(1) X B(2) C(4)"),
+            [". InsertedAt", "[2->3][0] ValueNum"]
+        );
+        // Values between aligned ones are paired by their shape.
+        assert_eq!(
+            seq("A(1) B { x: 1 }", r#"A(1) "s" B { x: 2 }"#),
+            [". InsertedAt", "[1->2].x ValueNum"]
+        );
+    }
+
+    #[test]
+    fn cross_format() {
+        let config = DiffConfig {
+            cross_format: true,
+            ..DiffConfig::default()
+        };
+        let check = |lhs: &str, rhs: AstItem, expected: &[&str]| {
+            let lhs = parse(lhs).unwrap();
+            assert_eq!(summary(&find_diff(&lhs, &rhs, &config)), expected, "{rhs}");
+        };
+        let json = |x| crate::parse_json(x).unwrap();
+        let ron = |x| crate::parse_ron(x).unwrap();
+
+        let order = r#"Order { id: 1, tags: ["a"], price: Some(2.5), state: Ready }"#;
+        check(
+            order,
+            json(r#"{"id": 1, "tags": ["a"], "price": 2.5, "state": "Ready"}"#),
+            &[],
+        );
+        check(
+            order,
+            json(r#"{"id": 2, "tags": ["a"], "price": 2.5, "state": "Done"}"#),
+            &[".id ValueNum", ".state AstItem"],
+        );
+        check(
+            order,
+            ron("Order(id: 1, tags: [\"b\"], price: Some(2.5), state: Ready)"),
+            &[".tags RemovedAt", ".tags InsertedAt"],
+        );
+        check(
+            order,
+            ron("(id: 1, tags: [], price: None, state: Ready)"),
+            &[".price RemovedAt", ".tags RemovedAt"],
+        );
+        check("Order { id: 1, .. }", ron("Order(id: 1, tags: [])"), &[]);
+
+        // Objects and maps with string keys are compared field by field.
+        let map = r#"{"a": 1, "b": 2}"#;
+        check(map, json(r#"{"a": 1, "b": 3}"#), &[".b ValueNum"]);
+        check("{ a: 1, .. }", json(map), &[]);
+        check("Foo(1)", json(r#"{"Foo": 2}"#), &[".Foo ValueNum"]);
+
+        // Unless they don't hold the same fields.
+        check(map, json(r#"{"a": 1}"#), &[". AstItem"]);
+        check(map, json(r#"{"a": 1, "b": 2, "c": 3}"#), &[". AstItem"]);
+        check(r#"{"a": 1, r"a": 1}"#, json(map), &[". AstItem"]);
+        check(r#"{1: 1, 2: 2}"#, json(map), &[". AstItem"]);
+
+        // Missing fields which are ignored don't matter.
+        let config = DiffConfig {
+            cross_format: true,
+            ..ignoring(&[".c"])
+        };
+        for (lhs, rhs) in [
+            (map, r#"{"a": 1, "b": 2, "c": 3}"#),
+            (r#"{"a": 1, "b": 2, "c": 3}"#, map),
+        ] {
+            let (lhs, rhs) = (parse(lhs).unwrap(), json(rhs));
+            assert!(find_diff(&lhs, &rhs, &config).is_empty(), "{lhs}");
+        }
+
+        // Sequences are compared item by item, and values other formats write as strings by text.
+        check(
+            "(1, [::1]:80, 1.5s)",
+            json(r#"[1, "[::1]:80", "1.5s"]"#),
+            &[],
+        );
+        check("{1, 2}", json("[2, 1]"), &[]);
+        check("(1, 2)", ron("[1, 3]"), &["[1] ValueNum"]);
+
+        // Numbers are compared by value.
+        check("[2.0, 1e3, -0.5]", json("[2, 1000, -5e-1]"), &[]);
+        check(
+            "[31, 1000, 8, 3, 1]",
+            ron("[0x1F, 1_000, 0o10, 0b11, +1]"),
+            &[],
+        );
+        check(
+            "(2.5, 31, -inf)",
+            ron("(2.4, 0x1E, inf)"),
+            &["[0] ValueNum", "[1] ValueNum", "[2] ValueNum"],
+        );
+        let (lhs, rhs) = (parse("(2.0, 31)").unwrap(), ron("(2, 0x1F)"));
+        let diffs = find_diff(&lhs, &rhs, &DiffConfig::default());
+        assert_eq!(summary(&diffs), ["[0] ValueNum", "[1] ValueNum"]);
+    }
+
+    #[test]
+    fn pair_equal_long_path() {
+        // Each item equals the one at the same index and the next one, except for the last which
+        // only equals the first: pairing it moves every other item by one.
+        let n = 2000;
+        let is_equal = (0..n)
+            .map(|l| {
+                (0..n)
+                    .map(|r| {
+                        if l == n - 1 {
+                            r == 0
+                        } else {
+                            r == l || r == l + 1
+                        }
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+
+        let pairs = pair_equal(&is_equal, n);
+        let expected = (0..n).map(|r| Some((r + n - 1) % n)).collect::<Vec<_>>();
+        assert_eq!(pairs, expected);
+    }
+}
@@ -0,0 +1,29 @@
+//! Extraction of values embedded in other kinds of output.
+
+use crate::{
+    ast::AstItem,
+    parse::{self, Spanned},
+};
+use chumsky::prelude::SimpleSpan;
+
+pub mod assert;
+pub mod dbg;
+pub mod log;
+pub mod trace;
+
+const LEVELS: [&str; 5] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+
+/// Parse the value starting at `offset`, returning it along with its span within `text`.
+///
+/// The value must be followed by whitespace, a separator, a closing delimiter or a backtick, so that
+/// a word in the middle of a sentence isn't mistaken for a value.
+fn parse_at(text: &str, offset: usize) -> Option<Spanned<AstItem<'_>>> {
+    let (value, span) = parse::parse_prefix(&text[offset..])?;
+
+    let span = SimpleSpan::new(offset + span.start, offset + span.end);
+    match text[span.end..].chars().next() {
+        None => Some((value, span)),
+        Some(x) if x.is_whitespace() || ",;)]}`".contains(x) => Some((value, span)),
+        Some(_) => None,
+    }
+}
@@ -0,0 +1,190 @@
+//! Failed `assert_eq!` (and `assert_ne!`) messages in the output of `cargo test`.
+//!
+//! Both the current format (`  left: value`) and the one used before Rust 1.73 (``  left: `value`,``)
+//! are supported.
+
+use super::parse_at;
+use crate::ast::AstItem;
+
+/// The values of a failed assertion.
+#[derive(Clone, Debug)]
+pub struct AssertFailure<'a> {
+    /// The test that failed, if it could be found.
+    pub test: Option<&'a str>,
+    /// The line number (starting at 1) of the `left:` value.
+    pub line: usize,
+    /// Both values, or `None` if one of them couldn't be parsed.
+    pub values: Option<(AstItem<'a>, AstItem<'a>)>,
+}
+
+/// Find every failed assertion in the output of some tests.
+pub fn scan(text: &str) -> Vec<AssertFailure<'_>> {
+    let mut failures = Vec::new();
+    let mut test = None;
+    let mut left = None;
+
+    let mut offset = 0;
+    for (line_idx, line) in text.split_inclusive('\n').enumerate() {
+        let line_offset = offset;
+        offset += line.len();
+
+        if let Some(name) = parse_test_name(line) {
+            test = Some(name);
+            left = None;
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let trimmed_offset = line_offset + line.len() - trimmed.len();
+        if trimmed.starts_with("left: ") {
+            left = Some((
+                line_idx + 1,
+                parse_value(text, trimmed_offset + "left: ".len()),
+            ));
+        } else if trimmed.starts_with("right: ") {
+            let Some((line, left)) = left.take() else {
+                continue;
+            };
+
+            let right = parse_value(text, trimmed_offset + "right: ".len());
+            failures.push(AssertFailure {
+                test,
+                line,
+                values: left.zip(right),
+            });
+        }
+    }
+
+    failures
+}
+
+/// The name of the test a line introduces, if any.
+fn parse_test_name(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix("thread '") {
+        let (name, rest) = rest.split_once('\'')?;
+        // Recent versions of Rust print the id of the thread after its name: `'name' (12) panicked`.
+        let rest = match rest.strip_prefix(" (") {
+            Some(rest) => rest
+                .trim_start_matches(|x: char| x.is_ascii_digit())
+                .strip_prefix(')')?,
+            None => rest,
+        };
+        return rest.starts_with(" panicked").then_some(name);
+    }
+
+    line.strip_prefix("---- ")?
+        .trim_end()
+        .strip_suffix(" stdout ----")
+}
+
+fn parse_value(text: &str, offset: usize) -> Option<AstItem<'_>> {
+    let offset = if text[offset..].starts_with('`') {
+        offset + 1
+    } else {
+        offset
+    };
+
+    // The value takes the rest of the line (up to the closing backtick in the old format).
+    let (value, span) = parse_at(text, offset)?;
+    let line_end = text[span.end..].lines().next().unwrap_or_default();
+    (line_end.trim().is_empty() || line_end.starts_with('`')).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    /// The test, line and values of each failure, like `tests::a:12 1 | 2`.
+    fn summary(text: &str) -> Vec<String> {
+        scan(text)
+            .into_iter()
+            .map(|x| {
+                let values = match x.values {
+                    Some((l, r)) => format!("{l} | {r}"),
+                    None => "?".to_owned(),
+                };
+                format!("{}:{} {values}", x.test.unwrap_or("?"), x.line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn current_format() {
+        let text = r#"
+running 2 tests
+test tests::a ... FAILED
+test tests::b ... FAILED
+
+failures:
+
+---- tests::a stdout ----
+
+thread 'tests::a' (1234) panicked at src/lib.rs:5:9:
+assertion `left == right` failed
+  left: Point { x: 1, y: [2, 3] }
+ right: Point { x: 1, y: [2, 4] }
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+---- tests::b stdout ----
+
+thread 'tests::b' panicked at src/lib.rs:10:9:
+assertion `left != right` failed: ids must differ
+  left: "a b"
+ right: "a b"
+"#;
+        assert_eq!(
+            summary(text),
+            [
+                "tests::a:12 Point { x: 1, y: [2, 3] } | Point { x: 1, y: [2, 4] }",
+                r#"tests::b:20 "a b" | "a b""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn backtick_format() {
+        let text = r#"
+---- tests::a stdout ----
+thread 'tests::a' panicked at 'assertion failed: `(left == right)`
+  left: `Some([1, 2])`,
+ right: `None`', src/lib.rs:5:9
+"#;
+        let failures = scan(text);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].test, Some("tests::a"));
+        assert_eq!(
+            failures[0].values,
+            Some((parse("Some([1, 2])").unwrap(), parse("None").unwrap()))
+        );
+    }
+
+    #[test]
+    fn unparsable_values() {
+        let text = "
+thread 'tests::a' panicked at src/lib.rs:5:9:
+assertion `left == right` failed
+  left: Point { x: 1
+ right: Point { x: 2 }
+thread 'tests::b' panicked at src/lib.rs:7:9:
+ right: 2
+";
+        // The failure is still reported, without values; a lone `right:` isn't a failure.
+        assert_eq!(summary(text), ["tests::a:4 ?"]);
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(parse_test_name("---- a::b stdout ----\n"), Some("a::b"));
+        assert_eq!(
+            parse_test_name("thread 'a::b' panicked at src/lib.rs:1:1:\n"),
+            Some("a::b")
+        );
+        assert_eq!(
+            parse_test_name("thread 'a::b' (77) panicked at src/lib.rs:1:1:\n"),
+            Some("a::b")
+        );
+        assert_eq!(parse_test_name("thread 'main' (x) panicked"), None);
+        assert_eq!(parse_test_name("test a::b ... FAILED\n"), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Values printed by the `dbg!` macro.
+//!
+//! Each record looks like `[src/main.rs:42:5] expr = value`, where the value may span multiple
+//! lines when pretty-printed.
+
+use super::parse_at;
+use crate::ast::AstItem;
+use std::collections::HashMap;
+
+/// A value printed by `dbg!`.
+#[derive(Clone, Debug)]
+pub struct DbgRecord<'a> {
+    /// Where `dbg!` was called (`src/main.rs:42` or `src/main.rs:42:5`).
+    pub location: &'a str,
+    /// The expression that was printed.
+    pub expr: &'a str,
+    /// How many records with the same location and expression came before this one.
+    pub occurrence: usize,
+    pub value: AstItem<'a>,
+}
+
+impl<'a> DbgRecord<'a> {
+    fn key(&self) -> (&'a str, &'a str, usize) {
+        (self.location, self.expr, self.occurrence)
+    }
+}
+
+/// Find every `dbg!` record in some output, ignoring everything else.
+pub fn scan(text: &str) -> Vec<DbgRecord<'_>> {
+    let mut records = Vec::new();
+    let mut occurrences = HashMap::<_, usize>::new();
+
+    let mut offset = 0;
+    while offset < text.len() {
+        let line = text[offset..]
+            .split_inclusive('\n')
+            .next()
+            .unwrap_or_default();
+        match parse_record(text, offset, line) {
+            Some((location, expr, value, end)) => {
+                let occurrence = occurrences.entry((location, expr)).or_default();
+                records.push(DbgRecord {
+                    location,
+                    expr,
+                    occurrence: *occurrence,
+                    value,
+                });
+                *occurrence += 1;
+
+                offset = end;
+            }
+            None => offset += line.len(),
+        }
+    }
+
+    records
+}
+
+/// Pair the records of two runs by their location, expression and occurrence.
+///
+/// Records are returned in the order of the left run, followed by the ones only present in the
+/// right run.
+pub fn match_records<'s, 'a>(
+    lhs: &'s [DbgRecord<'a>],
+    rhs: &'s [DbgRecord<'a>],
+) -> Vec<(Option<&'s DbgRecord<'a>>, Option<&'s DbgRecord<'a>>)> {
+    let mut rhs_by_key = rhs.iter().map(|x| (x.key(), x)).collect::<HashMap<_, _>>();
+
+    let mut pairs = lhs
+        .iter()
+        .map(|x| (Some(x), rhs_by_key.remove(&x.key())))
+        .collect::<Vec<_>>();
+    pairs.extend(
+        rhs.iter()
+            .filter(|x| rhs_by_key.contains_key(&x.key()))
+            .map(|x| (None, Some(x))),
+    );
+
+    pairs
+}
+
+/// Parse the record starting at `offset`, returning its location, expression, value and where it
+/// ends.
+fn parse_record<'a>(
+    text: &'a str,
+    offset: usize,
+    line: &'a str,
+) -> Option<(&'a str, &'a str, AstItem<'a>, usize)> {
+    let (location, rest) = line.strip_prefix('[')?.split_once("] ")?;
+    let (_, line_no) = location.split_once(':')?;
+    if !line_no.split(':').all(|x| x.parse::<u32>().is_ok()) {
+        return None;
+    }
+
+    // The expression may contain ` = ` itself (`{ let a = 1; a } = 1`), so try every one until the
+    // rest of the record is a value.
+    let rest_offset = offset + line.len() - rest.len();
+    rest.match_indices(" = ").find_map(|(idx, sep)| {
+        let (value, span) = parse_at(text, rest_offset + idx + sep.len())?;
+        let line_end = text[span.end..].lines().next().unwrap_or_default();
+        line_end
+            .trim()
+            .is_empty()
+            .then(|| (location, &rest[..idx], value, span.end))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const LHS: &str = "\
+   Compiling app v0.1.0
+[src/main.rs:4:5] &config = Config {
+    name: \"a\",
+    retries: 3,
+}
+warning: unused variable
+[src/main.rs:9:9] i = 0
+[src/main.rs:9:9] i = 1
+[src/lib.rs:2] { let a = 1; a } = 1
+";
+
+    const RHS: &str = "\
+[src/main.rs:4:5] &config = Config {
+    name: \"b\",
+    retries: 3,
+}
+[src/main.rs:9:9] i = 0
+[src/main.rs:12:1] done = true
+";
+
+    #[test]
+    fn records() {
+        let records = scan(LHS);
+        let found: Vec<_> = records
+            .iter()
+            .map(|x| (x.location, x.expr, x.occurrence))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("src/main.rs:4:5", "&config", 0),
+                ("src/main.rs:9:9", "i", 0),
+                ("src/main.rs:9:9", "i", 1),
+                ("src/lib.rs:2", "{ let a = 1; a }", 0),
+            ]
+        );
+        assert_eq!(
+            records[0].value,
+            parse(r#"Config { name: "a", retries: 3 }"#).unwrap()
+        );
+        assert_eq!(records[2].value, parse("1").unwrap());
+        assert_eq!(records[3].value, parse("1").unwrap());
+    }
+
+    #[test]
+    fn not_records() {
+        for text in [
+            "[src/main.rs] x = 1",
+            "[src/main.rs:a] x = 1",
+            "[src/main.rs:1] x",
+            "[src/main.rs:1] x = not a value",
+            "  [src/main.rs:1] x = 1",
+        ] {
+            assert!(scan(text).is_empty(), "{text}");
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let (lhs, rhs) = (scan(LHS), scan(RHS));
+        let found: Vec<_> = match_records(&lhs, &rhs)
+            .into_iter()
+            .map(|(l, r)| {
+                let record = l.or(r).unwrap();
+                (record.location, record.occurrence, l.is_some(), r.is_some())
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("src/main.rs:4:5", 0, true, true),
+                ("src/main.rs:9:9", 0, true, true),
+                ("src/main.rs:9:9", 1, true, false),
+                ("src/lib.rs:2", 0, true, false),
+                ("src/main.rs:12:1", 0, false, true),
+            ]
+        );
+    }
+}
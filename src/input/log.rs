@@ -0,0 +1,262 @@
+//! Values embedded in free-form log lines.
+//!
+//! Lines are expected to look like `2026-10-01T12:00:00Z INFO handler: state=Session { .. }`, where
+//! both the timestamp and the level are optional. Values are looked for after `=` (`key=value`),
+//! after `: ` (`key: value`) and at opening brackets (along with the type name before them).
+
+use super::{parse_at, LEVELS};
+use crate::ast::AstItem;
+
+/// A value found in a log.
+#[derive(Clone, Debug)]
+pub struct LogValue<'a> {
+    /// The line number (starting at 1).
+    pub line: usize,
+    /// The log target (usually the module path) of the line, if any.
+    pub target: Option<&'a str>,
+    /// The name the value was given (`key=value` or `key: value`), if any.
+    pub key: Option<&'a str>,
+    pub value: AstItem<'a>,
+}
+
+/// Which values to pick from a log.
+#[derive(Clone, Debug, Default)]
+pub struct LogSelector {
+    /// Only values logged by this target (or one of its submodules).
+    pub target: Option<String>,
+    /// Only values with this name.
+    pub key: Option<String>,
+    /// Only the nth matching value (starting at 0).
+    pub nth: Option<usize>,
+}
+
+impl LogSelector {
+    pub fn select<'s, 'a>(&self, values: &'s [LogValue<'a>]) -> Vec<&'s LogValue<'a>> {
+        let matches = values.iter().filter(|value| {
+            let target_matches = self.target.as_deref().is_none_or(|target| {
+                value.target.is_some_and(|x| {
+                    x.strip_prefix(target)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+                })
+            });
+            let key_matches = self.key.as_deref().is_none_or(|key| value.key == Some(key));
+
+            target_matches && key_matches
+        });
+
+        match self.nth {
+            Some(nth) => matches.skip(nth).take(1).collect(),
+            None => matches.collect(),
+        }
+    }
+}
+
+/// Find every value in a log.
+///
+/// Text that can't be parsed is skipped: scanning resumes at the next place a value could start.
+pub fn scan(text: &str) -> Vec<LogValue<'_>> {
+    let mut values = Vec::new();
+
+    for (line_idx, line) in text.split_inclusive('\n').enumerate() {
+        let (target, mut pos) = parse_header(line);
+
+        while let Some((start, key)) = find_candidate(line, pos) {
+            match parse_at(line, start) {
+                // Bare identifiers are too common in messages, so they need an explicit `key=`.
+                Some((AstItem::ItemUnitStruct(_), _)) if !line[..start].ends_with('=') => {
+                    pos = start + 1;
+                }
+                Some((value, span)) => {
+                    values.push(LogValue {
+                        line: line_idx + 1,
+                        target,
+                        key,
+                        value,
+                    });
+                    pos = span.end;
+                }
+                None => pos = start + 1,
+            }
+        }
+    }
+
+    values
+}
+
+/// Skip the timestamp and level of a line, returning its target (if any) and where the message
+/// starts.
+fn parse_header(line: &str) -> (Option<&str>, usize) {
+    let mut pos = 0;
+    let mut has_level = false;
+    for token in line.split_whitespace() {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        let word = token.trim_start_matches('[');
+
+        let is_timestamp = is_timestamp(word.trim_end_matches(']'));
+        let is_level = LEVELS
+            .iter()
+            .any(|level| word.trim_end_matches(']').eq_ignore_ascii_case(level));
+        if is_timestamp || is_level {
+            has_level |= is_level;
+            pos = offset + token.len();
+            continue;
+        }
+
+        // Without a level, the first word is more likely to be a name than a target.
+        return match word.strip_suffix(':').or_else(|| word.strip_suffix(']')) {
+            Some(target) if has_level && !target.is_empty() => (Some(target), offset + token.len()),
+            _ => (None, pos),
+        };
+    }
+
+    (None, pos)
+}
+
+/// Whether a word looks like a date or a time (`2026-10-01`, `12:00:00.123`, `2026/10/01`...).
+///
+/// Plain numbers are not timestamps: a line may well start with a count.
+fn is_timestamp(word: &str) -> bool {
+    word.starts_with(|x: char| x.is_ascii_digit())
+        && word.contains(['-', ':', '/'])
+        && word
+            .chars()
+            .all(|x| x.is_ascii_digit() || "-:/.,+TZ".contains(x))
+}
+
+/// Find the next position at or after `pos` where a value may start, along with its name.
+fn find_candidate(line: &str, pos: usize) -> Option<(usize, Option<&str>)> {
+    let (idx, ch) = line
+        .get(pos..)?
+        .char_indices()
+        .map(|(idx, ch)| (pos + idx, ch))
+        .find(|&(idx, ch)| match ch {
+            '=' => true,
+            ':' => line[idx + 1..].starts_with(' '),
+            '[' | '{' | '(' => true,
+            _ => false,
+        })?;
+
+    Some(match ch {
+        '=' => (idx + 1, key_before(line, idx)),
+        ':' => (idx + 2, key_before(line, idx)),
+        // Never back up past `pos`, where the last attempt failed.
+        _ => (
+            type_name_before(line, idx)
+                .filter(|&start| start >= pos)
+                .unwrap_or(idx),
+            None,
+        ),
+    })
+}
+
+/// Where the type name of the value opening at `bracket` starts, if it has one (`Foo(..)`,
+/// `Foo { .. }`).
+fn type_name_before(line: &str, bracket: usize) -> Option<usize> {
+    let end = match &line[bracket..bracket + 1] {
+        "(" => bracket,
+        "{" if line[..bracket].ends_with(' ') => bracket - 1,
+        "{" => return None,
+        _ => return None,
+    };
+    let start = line[..end]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, x)| x.is_alphanumeric() || x == '_')
+        .last()?
+        .0;
+
+    line[start..end]
+        .starts_with(|x: char| x.is_uppercase())
+        .then_some(start)
+}
+
+/// The identifier right before `end`, if any.
+fn key_before(line: &str, end: usize) -> Option<&str> {
+    let start = line[..end]
+        .char_indices()
+        .rev()
+        .find(|&(_, x)| !(x.is_alphanumeric() || x == '_' || x == '.'))
+        .map_or(0, |(idx, x)| idx + x.len_utf8());
+
+    (start < end).then(|| &line[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn headers() {
+        let cases = [
+            ("2026-10-01T12:00:00Z INFO app::db: a=1", Some("app::db")),
+            ("2026-10-01 12:00:00,123 [WARN] app: a=1", Some("app")),
+            ("[2026-10-01 12:00:00] ERROR app: a=1", Some("app")),
+            ("12:00:00.123 DEBUG app::db: a=1", Some("app::db")),
+            ("INFO app: a=1", Some("app")),
+        ];
+        for (line, target) in cases {
+            let (found, pos) = parse_header(line);
+            assert_eq!(found, target, "{line}");
+            assert_eq!(&line[pos..], " a=1", "{line}");
+        }
+
+        // Without a level, the first word is part of the message.
+        assert_eq!(parse_header("state: a=1"), (None, 0));
+
+        // A count is part of the message, not a timestamp.
+        assert_eq!(parse_header("42 INFO app: a=1"), (None, 0));
+        assert_eq!(parse_header("3 retries: a=1"), (None, 0));
+    }
+
+    #[test]
+    fn values() {
+        let text = "\
+2026-10-01T12:00:00Z INFO app::db: loaded rows=[1, 2] from Config { path: \"a\" }
+2026-10-01T12:00:01Z WARN app::http: retrying after Timeout, state=Idle
+2 apples and 3 pears
+2026-10-01T12:00:02Z INFO app::db: loaded rows=[3]
+";
+        let values = scan(text);
+        let found: Vec<_> = values
+            .iter()
+            .map(|x| (x.line, x.target, x.key, x.value.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, Some("app::db"), Some("rows"), "[1, 2]".into()),
+                (1, Some("app::db"), None, r#"Config { path: "a" }"#.into()),
+                (2, Some("app::http"), Some("state"), "Idle".into()),
+                (4, Some("app::db"), Some("rows"), "[3]".into()),
+            ]
+        );
+
+        // Non-ASCII text right before a value doesn't get in the way.
+        let values = scan("2026-10-01T12:00:00Z INFO app: état→{a: 1} “{b: 2} Réponse { c: 3 }\n");
+        let found: Vec<_> = values.iter().map(|x| &x.value).collect();
+        let expected = ["{ a: 1 }", "{ b: 2 }", "Réponse { c: 3 }"].map(|x| parse(x).unwrap());
+        assert_eq!(found, expected.iter().collect::<Vec<_>>());
+
+        // Scanning resumes after a value that can't be parsed.
+        let values = scan("Broken(} Done(1)\n");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].value, parse("Done(1)").unwrap());
+
+        let values = scan(text);
+        let selector = LogSelector {
+            target: Some("app".into()),
+            key: Some("rows".into()),
+            nth: Some(1),
+        };
+        let selected = selector.select(&values);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].value, parse("[3]").unwrap());
+
+        let selector = LogSelector {
+            target: Some("app::d".into()),
+            ..LogSelector::default()
+        };
+        assert!(selector.select(&values).is_empty());
+    }
+}
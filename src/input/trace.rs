@@ -0,0 +1,228 @@
+//! Events printed by the `fmt` layer of `tracing-subscriber`.
+//!
+//! Lines are expected to look like
+//! `2026-10-01T12:00:00Z  INFO conn{peer=10.0.0.1:80}:request{id=3}: server::http: done status=Ok`,
+//! where the timestamp, the spans and the target are optional but the level isn't.
+//!
+//! Every event becomes a struct named after its target, whose members are its fields along with
+//! `level`, `message` and `spans` (the structs of every span, from the outermost to the innermost).
+//! Fields printed using `Display` (`%value`) which don't parse as a value are kept as raw strings.
+
+use super::{parse_at, LEVELS};
+use crate::ast::{AstItem, ItemList, ItemStruct, ItemUnitStruct, StrKind, ValueStr};
+use std::{borrow::Cow, collections::BTreeMap};
+
+type Fields<'a> = BTreeMap<Cow<'a, str>, AstItem<'a>>;
+
+/// Find every event in a trace, ignoring the lines without a level.
+pub fn scan(text: &str) -> Vec<AstItem<'_>> {
+    text.lines()
+        .filter_map(|line| parse_event(line.trim_end()))
+        .collect()
+}
+
+fn parse_event(line: &str) -> Option<AstItem<'_>> {
+    let (level, pos) = parse_level(line)?;
+    let (mut spans, pos) = parse_spans(line, pos);
+
+    // A lone word without fields is far more likely to be the target than a span.
+    let (target, pos) = match spans.as_slice() {
+        [ItemStruct(name, fields, _)] if fields.is_empty() => {
+            let name = name.clone();
+            spans.clear();
+            (Some(name), pos)
+        }
+        _ => match parse_target(line, pos) {
+            Some((target, pos)) => (Some(target.into()), pos),
+            None => (None, pos),
+        },
+    };
+
+    // The fields come after the message, so the first position from which the rest of the line
+    // parses as fields is where the message ends.
+    let (message, mut fields) = line[pos..]
+        .char_indices()
+        .map(|(idx, _)| pos + idx)
+        .chain([line.len()])
+        .filter(|&x| x == pos || line[..x].ends_with(' '))
+        .find_map(|x| {
+            let (fields, _) = parse_fields(line, x, None)?;
+            Some((line[pos..x].trim(), fields))
+        })?;
+
+    fields.insert("level".into(), ItemUnitStruct(level.into()).into());
+    if !message.is_empty() {
+        fields.insert("message".into(), raw_str(message).into());
+    }
+    fields.insert(
+        "spans".into(),
+        ItemList(spans.into_iter().map(AstItem::from).collect()).into(),
+    );
+
+    Some(ItemStruct(target.unwrap_or("event".into()), fields, false).into())
+}
+
+/// Skip the timestamp of a line, returning its level and where the rest starts.
+fn parse_level(line: &str) -> Option<(&str, usize)> {
+    for token in line.split_whitespace() {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        if let Some(level) = LEVELS.iter().find(|x| token.eq_ignore_ascii_case(x)) {
+            let rest = &line[offset + token.len()..];
+            return Some((level, line.len() - rest.trim_start().len()));
+        }
+        if !token.starts_with(|x: char| x.is_ascii_digit()) {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// Parse a chain of spans (`outer{a=1}:inner: `), returning nothing unless it's complete.
+fn parse_spans(line: &str, pos: usize) -> (Vec<ItemStruct<'_>>, usize) {
+    let mut spans = Vec::new();
+    let mut cur = pos;
+    loop {
+        let rest = &line[cur..];
+        let name_len = rest
+            .find(|x: char| !(x.is_alphanumeric() || x == '_'))
+            .unwrap_or(rest.len());
+        if name_len == 0 {
+            break;
+        }
+
+        let mut next = cur + name_len;
+        let mut fields = BTreeMap::new();
+        if line[next..].starts_with('{') {
+            let Some((span_fields, end)) = parse_fields(line, next + 1, Some('}')) else {
+                break;
+            };
+            fields = span_fields;
+            next = end + 1;
+        }
+
+        let rest = &line[next..];
+        if !rest.starts_with(':') || rest.starts_with("::") {
+            break;
+        }
+        spans.push(ItemStruct(line[cur..cur + name_len].into(), fields, false));
+
+        cur = next + 1;
+        if cur == line.len() {
+            return (spans, cur);
+        } else if line[cur..].starts_with(' ') {
+            return (spans, cur + 1);
+        }
+    }
+
+    (Vec::new(), pos)
+}
+
+/// Parse a target (`server::http: `), returning it along with where the message starts.
+fn parse_target(line: &str, pos: usize) -> Option<(&str, usize)> {
+    let rest = &line[pos..];
+    let (target, end) = match rest.find(": ") {
+        Some(idx) => (&rest[..idx], pos + idx + 2),
+        None => (rest.strip_suffix(':')?, line.len()),
+    };
+
+    target
+        .split("::")
+        .all(|x| !x.is_empty() && x.chars().all(|x| x.is_alphanumeric() || x == '_'))
+        .then_some((target, end))
+}
+
+/// Parse a sequence of `key=value` fields until `close` (or the end of the line), returning them
+/// along with where they end.
+fn parse_fields(line: &str, pos: usize, close: Option<char>) -> Option<(Fields<'_>, usize)> {
+    let mut fields = BTreeMap::new();
+    let mut pos = pos;
+    loop {
+        pos = line.len() - line[pos..].trim_start_matches(' ').len();
+        let rest = &line[pos..];
+        if rest.is_empty() {
+            return close.is_none().then_some((fields, pos));
+        } else if close.is_some_and(|x| rest.starts_with(x)) {
+            return Some((fields, pos));
+        }
+
+        let key_len = field_key_len(rest)?;
+        let start = pos + key_len + 1;
+        let (value, end) = match parse_at(line, start) {
+            Some((value, span)) if is_field_end(line, span.end, close) => (value, span.end),
+            _ => {
+                let end = raw_value_end(line, start, close);
+                (raw_str(line[start..end].trim_end()).into(), end)
+            }
+        };
+
+        fields.insert(rest[..key_len].into(), value);
+        pos = end;
+    }
+}
+
+/// The length of the key if `text` starts with `key=`.
+fn field_key_len(text: &str) -> Option<usize> {
+    let len = text
+        .find(|x: char| !(x.is_alphanumeric() || x == '_' || x == '.'))
+        .unwrap_or(text.len());
+
+    (len > 0 && text[len..].starts_with('=')).then_some(len)
+}
+
+/// Whether a field value may end at `pos`.
+fn is_field_end(line: &str, pos: usize, close: Option<char>) -> bool {
+    let rest = line[pos..].trim_start_matches(' ');
+    match rest.chars().next() {
+        None => close.is_none(),
+        Some(x) if Some(x) == close => true,
+        Some(_) => rest.len() < line.len() - pos && field_key_len(rest).is_some(),
+    }
+}
+
+/// Where a field value printed using `Display` ends: at the next field, or at `close`.
+fn raw_value_end(line: &str, start: usize, close: Option<char>) -> usize {
+    line[start..]
+        .char_indices()
+        .map(|(idx, x)| (start + idx, x))
+        .find(|&(idx, x)| {
+            Some(x) == close || (x == ' ' && field_key_len(&line[idx + 1..]).is_some())
+        })
+        .map_or(line.len(), |(idx, _)| idx)
+}
+
+/// A raw string with as few hashes as `text` allows.
+fn raw_str(text: &str) -> ValueStr<'_> {
+    let hashes = (0..)
+        .find(|&n| !text.contains(&format!("\"{}", "#".repeat(n))))
+        .unwrap_or_default();
+
+    ValueStr(text.into(), StrKind::Raw(hashes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn non_ascii_event() {
+        let line =
+            "2026-10-01T12:00:00Z  INFO server::http: requête traitée 🎉 status=Ok elapsed=3µs";
+        let Some(AstItem::ItemStruct(event)) = parse_event(line) else {
+            panic!("expected an event");
+        };
+
+        assert_eq!(event.0, "server::http");
+        assert_eq!(event.1["message"].to_string(), r#"r"requête traitée 🎉""#);
+        assert_eq!(event.1["status"].to_string(), "Ok");
+        assert_eq!(event.1["elapsed"], parse("3µs").unwrap());
+    }
+
+    #[test]
+    fn raw_str_round_trips() {
+        let text = r####"a "# b "## c "### d"####;
+        let item = AstItem::from(raw_str(text));
+        assert_eq!(parse(&item.to_string()).unwrap(), item);
+    }
+}
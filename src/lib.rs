@@ -0,0 +1,50 @@
+//! Structural diffs of values printed using `Debug`.
+//!
+//! Text printed with `{:?}` or `{:#?}` is parsed into a tree ([`AstItem`]), which can then be
+//! compared against another tree, pretty-printed or converted into other formats:
+//!
+//! ```
+//! use debug_diff::{find_diff, parse, render::Report, DiffConfig};
+//!
+//! let left = parse("Point { x: 1, y: 2 }").unwrap();
+//! let right = parse("Point { x: 1, y: 3 }").unwrap();
+//!
+//! let diffs = find_diff(&left, &right, &DiffConfig::default());
+//! assert_eq!(diffs.len(), 1);
+//! print!("{}", Report(&diffs));
+//! ```
+//!
+//! In tests, [`assert_debug_eq!`] does the same for any two values implementing `Debug`, and
+//! [`debug_snapshot!`] compares a value against a [snapshot] stored in a file.
+//!
+//! The documented API follows semver. The structs of the AST expose what they hold, so changing
+//! it is a breaking change, while new kinds of values, differences and options aren't: these
+//! enums and the configurations are `#[non_exhaustive]`. The `input` module, which extracts values
+//! from other kinds of output for the command-line tool, isn't covered.
+
+pub use self::{
+    ast::AstItem,
+    diff::{
+        find_diff, find_diff_at, find_diff_seq, DiffConfig, DiffItem, DiffPath, DiffPathSegment,
+        DiffPayload,
+    },
+    parse::{parse, parse_document, parse_json, parse_ron, ParseError},
+};
+
+pub mod ast;
+pub mod convert;
+pub mod de;
+pub mod diff;
+#[doc(hidden)]
+pub mod input;
+#[doc(hidden)]
+pub mod macros;
+pub mod normalize;
+pub mod parse;
+pub mod path;
+pub mod query;
+pub mod render;
+pub mod ser;
+pub mod snapshot;
+mod util;
+pub mod visit;
@@ -0,0 +1,66 @@
+//! Support code for the assertion macros.
+
+use crate::{find_diff, parse, render::Report, DiffConfig};
+use std::fmt::{self, Debug};
+
+/// Asserts that two values are equal once printed using `Debug`, like `assert_eq!` does.
+///
+/// On failure, both values are parsed and the panic message lists their differences instead of
+/// repeating them in full. If either one can't be parsed, both are printed as they are.
+///
+/// Values whose only differences are ignored by default (addresses, closures...) are considered
+/// equal.
+///
+/// ```
+/// use debug_diff::assert_debug_eq;
+///
+/// #[derive(Debug)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// assert_debug_eq!(Point { x: 1, y: 2 }, Point { x: 1, y: 2 });
+/// ```
+///
+/// ```should_panic
+/// # use debug_diff::assert_debug_eq;
+/// assert_debug_eq!(vec![1, 2, 3], vec![1, 2, 4], "step {}", 3);
+/// ```
+#[macro_export]
+macro_rules! assert_debug_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => $crate::macros::assert_debug_eq(left, right, None),
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                $crate::macros::assert_debug_eq(left, right, Some(format_args!($($arg)+)))
+            }
+        }
+    };
+}
+
+#[track_caller]
+pub fn assert_debug_eq(left: &dyn Debug, right: &dyn Debug, args: Option<fmt::Arguments>) {
+    let (left, right) = (format!("{left:?}"), format!("{right:?}"));
+    if left == right {
+        return;
+    }
+
+    let message = match args {
+        Some(args) => format!("assertion `left == right` failed: {args}"),
+        None => "assertion `left == right` failed".to_owned(),
+    };
+    match (parse(&left), parse(&right)) {
+        (Ok(ast_left), Ok(ast_right)) => {
+            let diffs = find_diff(&ast_left, &ast_right, &DiffConfig::default());
+            if !diffs.is_empty() {
+                panic!("{message}\n{}", Report(&diffs));
+            }
+        }
+        _ => panic!("{message}\n  left: {left}\n right: {right}"),
+    }
+}
@@ -1,126 +1,442 @@
 #![feature(generators, generator_trait, iter_from_generator)]
 
-use crate::diff::DiffPayload;
-use chumsky::Parser;
+use crate::cmdline::{CmdLine, Command, Format, InputFormat};
+use clap::Parser as _;
+use debug_diff::{
+    ast::{AstItem, Pretty, PrettyConfig},
+    convert, find_diff, find_diff_at, find_diff_seq, input,
+    input::log::LogSelector,
+    normalize::Rules,
+    parse,
+    query::{Match, Query},
+    render::{PathText, Report},
+    snapshot, DiffConfig, DiffItem, DiffPath, DiffPathSegment, DiffPayload, ParseError,
+};
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    path::Path,
+};
 
-mod ast;
-mod diff;
-mod parse;
-mod util;
+mod cmdline;
 
 fn main() {
-    let data_left = std::fs::read_to_string(std::env::args().nth(1).unwrap()).unwrap();
-    let data_right = std::fs::read_to_string(std::env::args().nth(2).unwrap()).unwrap();
+    let args = CmdLine::parse();
+    match &args.command {
+        Some(Command::Assert { input, diff }) => {
+            run_assert(input.as_deref(), &diff.to_config(), diff.rules.as_ref())
+        }
+        Some(Command::Convert { input, to }) => run_convert(input.as_deref(), *to),
+        Some(Command::Fmt {
+            input,
+            indent,
+            width,
+            sort,
+        }) => {
+            let mut config = PrettyConfig::default();
+            config.indent = *indent;
+            config.width = *width;
+            config.sort = *sort;
+            run_fmt(input.as_deref(), &config);
+        }
+        Some(Command::Query {
+            query,
+            input,
+            format,
+        }) => run_query(query, input.as_deref(), *format),
+        Some(Command::Review { dir }) => run_review(dir),
+        None => run_diff(args),
+    }
+}
+
+fn read_input(input: Option<&Path>) -> String {
+    match input {
+        Some(path) => std::fs::read_to_string(path).unwrap(),
+        None => io::read_to_string(io::stdin()).unwrap(),
+    }
+}
+
+fn run_assert(input: Option<&Path>, config: &DiffConfig, rules: Option<&Rules>) {
+    let data = read_input(input);
 
-    let (ast_left, errors_left) = parse::parser().parse(&data_left).into_output_errors();
-    let (ast_right, errors_right) = parse::parser().parse(&data_right).into_output_errors();
+    let mut failures = input::assert::scan(&data);
+    println!("Found {} failed assertions.", failures.len());
 
-    match (&ast_left, errors_left.as_slice()) {
-        (Some(_), &[]) => println!(" left: ok"),
-        (None, x) if !x.is_empty() => println!(" left: err: {x:?}"),
-        _ => todo!(),
+    for (left, right) in failures.iter_mut().filter_map(|x| x.values.as_mut()) {
+        normalize(rules, left);
+        normalize(rules, right);
     }
-    match (&ast_right, errors_right.as_slice()) {
-        (Some(_), &[]) => println!("right: ok"),
-        (None, x) if !x.is_empty() => println!("right: err: {x:?}"),
-        _ => todo!(),
+    for failure in failures {
+        println!();
+        println!(
+            "{} (line {}):",
+            failure.test.unwrap_or("<unknown test>"),
+            failure.line
+        );
+        match failure.values {
+            Some((left, right)) => print!("{}", Report(&find_diff(&left, &right, config))),
+            None => println!("The values couldn't be parsed."),
+        }
     }
+}
+
+fn run_convert(input: Option<&Path>, format: Format) {
+    let data = read_input(input);
+    let ast = parse_or_exit(&data);
 
-    let (ast_left, ast_right) = match (ast_left, ast_right) {
-        (Some(x), Some(y)) => (x, y),
-        _ => return,
+    let text = match format {
+        Format::Json => serde_json::to_string_pretty(&convert::to_value(&ast))
+            .map_err(|error| error.to_string()),
+        Format::Yaml => {
+            serde_yaml::to_string(&convert::to_value(&ast)).map_err(|error| error.to_string())
+        }
+        Format::Ron => Ok(convert::to_ron(&ast)),
     };
+    match text {
+        Ok(text) => println!("{}", text.trim_end()),
+        Err(error) => {
+            eprintln!("err: {error}");
+            std::process::exit(1);
+        }
+    }
+}
 
-    let ast_diff = diff::find_diff(&ast_left, &ast_right);
-    println!("Found {} differences.", ast_diff.len());
-    println!();
-
-    // println!(" left: {ast_left}");
-    // println!();
-    // println!("right: {ast_right}");
-    // panic!();
-
-    for diff in ast_diff {
-        match diff.diff {
-            DiffPayload::AstItem(l, r) => {
-                // TODO: Improve type name.
-                println!(
-                    "  - Type mismatch: expected {r:?}, but got {l:?} at {:?}",
-                    diff.path
-                );
-            }
-            DiffPayload::ItemStruct(l, r) => {
-                println!(
-                    "  - Struct type mismatch: expected {}, but got {} at {:?}",
-                    r.0, l.0, diff.path
+fn run_fmt(input: Option<&Path>, config: &PrettyConfig) {
+    let data = read_input(input);
+    let ast = parse_or_exit(&data);
+
+    println!("{}", Pretty(&ast, config));
+}
+
+fn run_review(dir: &Path) {
+    let pending = snapshot::pending(dir).unwrap();
+    println!("Found {} pending snapshots.", pending.len());
+
+    let mut lines = io::stdin().lines();
+    for new_path in pending {
+        let path = new_path.with_extension("");
+        let new = std::fs::read_to_string(&new_path).unwrap();
+        let old = std::fs::read_to_string(&path).unwrap_or_default();
+
+        println!();
+        println!("{}:", path.display());
+        match (parse::parse(&new), parse::parse(&old)) {
+            (Ok(ast_new), Ok(ast_old)) => {
+                print!(
+                    "{}",
+                    Report(&find_diff(&ast_new, &ast_old, &DiffConfig::default()))
                 );
-                if l.0 == r.0 {
-                    println!("      Note: The internal structure differs.");
-                }
             }
-            DiffPayload::ItemTuple(l, r) => {
-                println!(
-                    "  - Tuple type mismatch: expected {} elements, but got {} at {:?}",
-                    r.0.len(),
-                    l.0.len(),
-                    diff.path
-                );
+            _ => print!("  new: {new}"),
+        }
+
+        loop {
+            print!("[a]ccept, [r]eject or [s]kip? ");
+            io::stdout().flush().unwrap();
+            let Some(line) = lines.next() else {
+                return;
+            };
+
+            match line.unwrap().trim() {
+                "a" => snapshot::accept(&new_path).unwrap(),
+                "r" => snapshot::reject(&new_path).unwrap(),
+                "s" => {}
+                _ => continue,
             }
-            DiffPayload::ItemTupleStruct(l, r) => {
-                println!(
-                    "  - Tuple struct type mismatch: expected {}, but got {} at {:?}",
-                    r.0, l.0, diff.path
-                );
-                if l.0 == r.0 {
-                    println!("      Note: The internal structure differs.");
+            break;
+        }
+    }
+}
+
+fn parse_or_exit(data: &str) -> AstItem<'_> {
+    parse::parse(data).unwrap_or_else(|error| {
+        eprintln!("err: {error}");
+        std::process::exit(1);
+    })
+}
+
+fn run_diff(args: CmdLine) {
+    let (left, right) = (args.left.unwrap(), args.right.unwrap());
+    let data_left = std::fs::read_to_string(&left).unwrap();
+    let data_right = std::fs::read_to_string(&right).unwrap();
+
+    let mut config = args.diff.to_config();
+    let rules = args.diff.rules.as_ref();
+
+    if args.document {
+        let docs_left = parse_input(" left", parse::parse_document(&data_left, args.skip_text));
+        let docs_right = parse_input("right", parse::parse_document(&data_right, args.skip_text));
+        let (Some(docs_left), Some(docs_right)) = (docs_left, docs_right) else {
+            return;
+        };
+
+        let mut items_left = docs_left.into_iter().map(|(x, _)| x).collect::<Vec<_>>();
+        let mut items_right = docs_right.into_iter().map(|(x, _)| x).collect::<Vec<_>>();
+        items_left
+            .iter_mut()
+            .chain(&mut items_right)
+            .for_each(|x| normalize(rules, x));
+        println!(
+            "Comparing {} values against {} values.",
+            items_left.len(),
+            items_right.len()
+        );
+
+        print!(
+            "{}",
+            Report(&find_diff_seq(&items_left, &items_right, &config))
+        );
+    } else if args.log {
+        let selector = LogSelector {
+            target: args.target,
+            key: args.key,
+            nth: args.nth,
+        };
+
+        let values_left = input::log::scan(&data_left);
+        let values_right = input::log::scan(&data_right);
+        let selected_left = selector.select(&values_left);
+        let selected_right = selector.select(&values_right);
+        for (name, values, selected) in [
+            (" left", &values_left, &selected_left),
+            ("right", &values_right, &selected_right),
+        ] {
+            let lines = selected
+                .iter()
+                .map(|x| x.line.to_string())
+                .collect::<Vec<_>>();
+            println!(
+                "{name}: found {} values, selected {} (lines {})",
+                values.len(),
+                selected.len(),
+                lines.join(", ")
+            );
+        }
+
+        let mut items_left = selected_left
+            .into_iter()
+            .map(|x| x.value.clone())
+            .collect::<Vec<_>>();
+        let mut items_right = selected_right
+            .into_iter()
+            .map(|x| x.value.clone())
+            .collect::<Vec<_>>();
+        items_left
+            .iter_mut()
+            .chain(&mut items_right)
+            .for_each(|x| normalize(rules, x));
+        print!(
+            "{}",
+            Report(&find_diff_seq(&items_left, &items_right, &config))
+        );
+    } else if args.dbg {
+        let mut records_left = input::dbg::scan(&data_left);
+        let mut records_right = input::dbg::scan(&data_right);
+        for record in records_left.iter_mut().chain(&mut records_right) {
+            normalize(rules, &mut record.value);
+        }
+        println!(" left: found {} records", records_left.len());
+        println!("right: found {} records", records_right.len());
+
+        for pair in input::dbg::match_records(&records_left, &records_right) {
+            match pair {
+                (Some(l), Some(r)) => {
+                    let ast_diff = find_diff(&l.value, &r.value, &config);
+                    if !ast_diff.is_empty() {
+                        println!();
+                        println!("[{}] {} (#{}):", l.location, l.expr, l.occurrence);
+                        print!("{}", Report(&ast_diff));
+                    }
                 }
+                (Some(l), None) => {
+                    println!();
+                    println!(
+                        "[{}] {} (#{}): only in left",
+                        l.location, l.expr, l.occurrence
+                    );
+                }
+                (None, Some(r)) => {
+                    println!();
+                    println!(
+                        "[{}] {} (#{}): only in right",
+                        r.location, r.expr, r.occurrence
+                    );
+                }
+                (None, None) => unreachable!(),
             }
-            DiffPayload::ItemUnitStruct(l, r) => {
-                println!(
-                    "  - Enum or type mismatch: expected {}, but got {} at {:?}",
-                    r.0, l.0, diff.path
-                );
-            }
-            DiffPayload::ValueBool(l, r) => {
-                println!(
-                    "  - Boolean mismatch: expected {}, but got {} at {:?}.",
-                    r.0, l.0, diff.path
-                );
-            }
-            DiffPayload::ValueNum(l, r) => {
-                println!(
-                    "  - Number mismatch: expected {}, but got {} at {:?}.",
-                    r.0, l.0, diff.path
-                );
-            }
-            DiffPayload::ValueStr(l, r) => {
-                println!(
-                    "  - String mismatch: expected \"{}\", but got \"{}\" at {:?}.",
-                    r.0, l.0, diff.path
-                );
-            }
-            DiffPayload::InsertedAt(i, r) => {
-                println!(
-                    "  - Source set (or option) is missing an item at index {i}: {r:?} at {:?}",
-                    diff.path
-                );
+        }
+    } else if args.trace {
+        let mut events_left = input::trace::scan(&data_left);
+        let mut events_right = input::trace::scan(&data_right);
+        events_left
+            .iter_mut()
+            .chain(&mut events_right)
+            .for_each(|x| normalize(rules, x));
+        println!(" left: found {} events", events_left.len());
+        println!("right: found {} events", events_right.len());
+
+        print!(
+            "{}",
+            Report(&find_diff_seq(&events_left, &events_right, &config))
+        );
+    } else {
+        let left_format = args
+            .left_format
+            .unwrap_or_else(|| InputFormat::from_path(&left));
+        let right_format = args
+            .right_format
+            .unwrap_or_else(|| InputFormat::from_path(&right));
+        config.cross_format = left_format != right_format;
+
+        let ast_left = parse_input(" left", parse_value(left_format, &data_left));
+        let ast_right = parse_input("right", parse_value(right_format, &data_right));
+        let (Some(mut ast_left), Some(mut ast_right)) = (ast_left, ast_right) else {
+            return;
+        };
+        normalize(rules, &mut ast_left);
+        normalize(rules, &mut ast_right);
+
+        match &args.only {
+            Some(query) => {
+                let matches_left = query.select(&ast_left);
+                let matches_right = query.select(&ast_right);
+                for (name, matches) in [(" left", &matches_left), ("right", &matches_right)] {
+                    let paths = matches
+                        .iter()
+                        .map(|(path, _)| PathText(path).to_string())
+                        .collect::<Vec<_>>();
+                    println!(
+                        "{name}: selected {} values ({})",
+                        matches.len(),
+                        paths.join(", ")
+                    );
+                }
+
+                let diffs = diff_matches(&matches_left, &matches_right, &config);
+                print!("{}", Report(&diffs));
             }
-            DiffPayload::RemovedAt(i, l) => {
-                println!(
-                    "  - Source set (or option) has an extra item at index {i}: {l:?} at {:?}",
-                    diff.path
-                );
+            None => print!("{}", Report(&find_diff(&ast_left, &ast_right, &config))),
+        }
+    }
+}
+
+/// Compare the values selected on each side, pairing those at the same path (or at paths which only
+/// differ by their indices), and reporting the others as removed or inserted.
+fn diff_matches<'a, 'b>(
+    matches_left: &[Match<'a, 'b>],
+    matches_right: &[Match<'a, 'b>],
+    config: &DiffConfig,
+) -> Vec<DiffItem<'a, 'b>> {
+    let mut paired_left = vec![None; matches_left.len()];
+    let mut paired_right = vec![false; matches_right.len()];
+    for same_path in [true, false] {
+        for (l, (lhs_path, _)) in matches_left.iter().enumerate() {
+            if paired_left[l].is_some() {
+                continue;
             }
-            DiffPayload::InsertedPair(k, r) => {
-                println!("  - Source mapping is missing an entry at {:?}:", diff.path);
-                println!("      Key  : {k}");
-                println!("      Value: {r}");
+            let pair = matches_right
+                .iter()
+                .enumerate()
+                .find_map(|(r, (rhs_path, _))| {
+                    let path = if same_path {
+                        (lhs_path == rhs_path).then(|| lhs_path.clone())
+                    } else {
+                        merge_paths(lhs_path, rhs_path)
+                    };
+                    path.filter(|_| !paired_right[r]).map(|path| (r, path))
+                });
+            if let Some((r, path)) = pair {
+                paired_right[r] = true;
+                paired_left[l] = Some((r, path));
             }
-            DiffPayload::RemovedPair(k, l) => {
-                println!("  - Source mapping has an extra entry at {:?}", diff.path);
-                println!("      Key  : {k}");
-                println!("      Value: {l}");
+        }
+    }
+
+    let mut diffs = Vec::new();
+    for (l, pair) in paired_left.into_iter().enumerate() {
+        let (lhs_path, lhs) = &matches_left[l];
+        match pair {
+            Some((r, path)) => diffs.extend(find_diff_at(path, lhs, matches_right[r].1, config)),
+            None => diffs.push(DiffItem {
+                path: lhs_path[..lhs_path.len().saturating_sub(1)].to_vec(),
+                diff: DiffPayload::RemovedAt(last_index(lhs_path, l), Cow::Borrowed(*lhs)),
+            }),
+        }
+    }
+    for (r, (rhs_path, rhs)) in matches_right.iter().enumerate() {
+        if !paired_right[r] {
+            diffs.push(DiffItem {
+                path: rhs_path[..rhs_path.len().saturating_sub(1)].to_vec(),
+                diff: DiffPayload::InsertedAt(last_index(rhs_path, r), Cow::Borrowed(*rhs)),
+            });
+        }
+    }
+
+    diffs
+}
+
+/// The path with the indices of both sides, if both paths only differ by their indices.
+fn merge_paths<'a, 'b>(lhs: &DiffPath<'a, 'b>, rhs: &DiffPath<'a, 'b>) -> Option<DiffPath<'a, 'b>> {
+    if lhs.len() != rhs.len() {
+        return None;
+    }
+
+    lhs.iter()
+        .zip(rhs)
+        .map(|(l, r)| match (l, r) {
+            (DiffPathSegment::Index(l, _), DiffPathSegment::Index(_, r)) => {
+                Some(DiffPathSegment::Index(*l, *r))
             }
+            (l, r) => (l == r).then(|| l.clone()),
+        })
+        .collect()
+}
+
+/// The index of a value within its parent, or its position among the selected values.
+fn last_index(path: &DiffPath, position: usize) -> usize {
+    match path.last() {
+        Some(DiffPathSegment::Index(idx, _)) => *idx,
+        _ => position,
+    }
+}
+
+fn run_query(query: &Query, input: Option<&Path>, format: Option<InputFormat>) {
+    let data = read_input(input);
+    let format = format.unwrap_or_else(|| input.map_or(InputFormat::Debug, InputFormat::from_path));
+    let ast = match parse_value(format, &data) {
+        Ok(ast) => ast,
+        Err(error) => {
+            eprintln!("err: {error}");
+            std::process::exit(1);
         }
+    };
+
+    let config = PrettyConfig::default();
+    for (path, item) in query.select(&ast) {
+        println!("{}: {}", PathText(&path), Pretty(item, &config));
+    }
+}
+
+/// Rewrite a value with the rules given on the command line, if any.
+fn normalize(rules: Option<&Rules>, item: &mut AstItem) {
+    if let Some(rules) = rules {
+        rules.apply(item);
     }
 }
+
+fn parse_value(format: InputFormat, data: &str) -> Result<AstItem<'_>, ParseError> {
+    match format {
+        InputFormat::Debug => parse::parse(data),
+        InputFormat::Json => parse::parse_json(data),
+        InputFormat::Ron => parse::parse_ron(data),
+    }
+}
+
+fn parse_input<T>(name: &str, result: Result<T, ParseError>) -> Option<T> {
+    match &result {
+        Ok(_) => println!("{name}: ok"),
+        Err(error) => println!("{name}: err: {error}"),
+    }
+
+    result.ok()
+}
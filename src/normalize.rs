@@ -0,0 +1,431 @@
+//! Rules rewriting values before they're compared, so that both sides are normalized the same way
+//! and only the changes that matter are reported.
+//!
+//! Rules are read from a YAML (or JSON) list. Each one selects the values it applies to with any of:
+//!
+//! - `path`: a [path pattern](crate::path), every value matching if it's missing.
+//! - `match`: a regular expression found in the text of the value (the contents of a string, the
+//!   name of a unit struct, the text of a number...). Lists, structs and the like never match.
+//! - `kind`: `pointer` (`0x7ffd5a3c`), `uuid` (a string holding one) or `timestamp` (a string
+//!   holding an RFC 3339 date, or a `SystemTime` or `Instant`).
+//!
+//! and then does exactly one of:
+//!
+//! - `placeholder: name` replaces the value with `<name>`.
+//! - `replace: text` replaces every match of `match` within a string (`$1` standing for the first
+//!   group, and so on).
+//! - `round: n` rounds the numbers with a fractional part to `n` decimals.
+//! - `lowercase_keys: true` lowercases the field names of a struct and the string keys of a map.
+//!   Keys which only differ by their case (`Content-Type` and `content-type`) are left as they are
+//!   rather than merged, so that no value is lost.
+//!
+//! ```yaml
+//! - kind: uuid
+//!   placeholder: uuid
+//! - path: .requests[*].started
+//!   kind: timestamp
+//!   placeholder: timestamp
+//! - match: "/tmp/[a-z0-9]+"
+//!   replace: /tmp/dir
+//! - round: 3
+//! - path: .headers
+//!   lowercase_keys: true
+//! ```
+//!
+//! The children of a value are rewritten before the value itself, and rules apply in order, each
+//! one seeing the result of the previous ones. The keys of maps are never rewritten (apart from
+//! their case), since replacing them with placeholders would merge their entries: a rule with
+//! `kind: uuid` doesn't apply to the keys of a `HashMap<Uuid, _>`. For the same reason, the
+//! elements of sets (and anything within them) are left alone too.
+
+use crate::{
+    ast::*,
+    diff::DiffPath,
+    path::PathPattern,
+    visit::{walk_item_fold, Fold},
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::{borrow::Cow, collections::HashMap, error, fmt, mem, sync::OnceLock};
+
+/// A list of rules, as described above.
+#[derive(Clone, Debug)]
+pub struct Rules(Vec<Rule>);
+
+#[derive(Clone, Debug)]
+struct Rule {
+    path: Option<PathPattern>,
+    pattern: Option<Regex>,
+    kind: Option<ValueKind>,
+    action: Action,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ValueKind {
+    Pointer,
+    Uuid,
+    Timestamp,
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    Placeholder(String),
+    Replace(String),
+    Round(usize),
+    LowercaseKeys,
+}
+
+/// A rule the way it's written.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    path: Option<String>,
+    #[serde(rename = "match")]
+    pattern: Option<String>,
+    kind: Option<ValueKind>,
+    placeholder: Option<String>,
+    replace: Option<String>,
+    round: Option<usize>,
+    #[serde(default)]
+    lowercase_keys: bool,
+}
+
+impl Rules {
+    /// Read a list of rules written in YAML (or JSON, which YAML includes).
+    pub fn from_yaml(text: &str) -> Result<Self, RulesError> {
+        let specs: Vec<RuleSpec> =
+            serde_yaml::from_str(text).map_err(|error| RulesError(error.to_string()))?;
+        specs
+            .into_iter()
+            .enumerate()
+            .map(|(idx, spec)| {
+                Rule::new(spec)
+                    .map_err(|message| RulesError(format!("rule {}: {message}", idx + 1)))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// Rewrite the values of a tree matching the rules.
+    pub fn apply(&self, item: &mut AstItem) {
+        let owned = mem::replace(item, ItemEmpty.into());
+        *item = Normalize(self).fold_item(&mut Vec::new(), owned);
+    }
+}
+
+impl Rule {
+    fn new(spec: RuleSpec) -> Result<Self, String> {
+        let path = spec
+            .path
+            .map(|x| x.parse::<PathPattern>())
+            .transpose()
+            .map_err(|error| error.to_string())?;
+        let pattern = spec
+            .pattern
+            .map(|x| Regex::new(&x))
+            .transpose()
+            .map_err(|error| error.to_string())?;
+
+        let mut actions = Vec::new();
+        if let Some(name) = spec.placeholder {
+            // Placeholders are written within `<>`, which the name may already have.
+            let name = name.trim_start_matches('<').trim_end_matches('>');
+            if name.is_empty() || name.contains(['<', '>', '\n']) {
+                return Err(format!("invalid placeholder `{name}`"));
+            }
+            actions.push(Action::Placeholder(format!("<{name}>")));
+        }
+        if let Some(text) = spec.replace {
+            if pattern.is_none() {
+                return Err("`replace` needs a `match`".to_owned());
+            }
+            actions.push(Action::Replace(text));
+        }
+        if let Some(digits) = spec.round {
+            actions.push(Action::Round(digits));
+        }
+        if spec.lowercase_keys {
+            actions.push(Action::LowercaseKeys);
+        }
+
+        match <[Action; 1]>::try_from(actions) {
+            Ok([action]) => Ok(Self {
+                path,
+                pattern,
+                kind: spec.kind,
+                action,
+            }),
+            Err(_) => Err(
+                "expected exactly one of `placeholder`, `replace`, `round` or `lowercase_keys`"
+                    .to_owned(),
+            ),
+        }
+    }
+
+    fn selects(&self, path: &DiffPath, item: &AstItem) -> bool {
+        self.path.as_ref().is_none_or(|x| x.matches(path))
+            && self.kind.is_none_or(|x| x.matches(item))
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|x| leaf_text(item).is_some_and(|text| x.is_match(&text)))
+    }
+
+    fn apply<'a>(&self, item: AstItem<'a>) -> AstItem<'a> {
+        match (&self.action, item) {
+            (Action::Placeholder(name), _) => {
+                ValueOpaque(OpaqueKind::Placeholder, name.clone().into()).into()
+            }
+            (Action::Replace(text), AstItem::ValueStr(x)) => {
+                let pattern = self.pattern.as_ref().unwrap();
+                ValueStr::escape(&pattern.replace_all(&x.unescape(), text.as_str())).into()
+            }
+            (Action::Round(digits), AstItem::ValueNum(x)) if x.0.contains(['.', 'e', 'E']) => {
+                match x.0.parse::<f64>() {
+                    Ok(value) => ValueNum(format!("{value:.digits$}").into()).into(),
+                    Err(_) => x.into(),
+                }
+            }
+            (Action::LowercaseKeys, AstItem::ItemStruct(x)) => {
+                let lowercase = lowercase_keys(x.1.keys().map(|k| k.as_ref()));
+                let fields =
+                    x.1.into_iter()
+                        .map(|(k, v)| match lowercase.get(k.as_ref()) {
+                            Some(Some(lower)) => (Cow::Owned(lower.clone()), v),
+                            _ => (k, v),
+                        })
+                        .collect();
+                ItemStruct(x.0, fields, x.2).into()
+            }
+            (Action::LowercaseKeys, AstItem::ItemMap(x)) => {
+                let lowercase = lowercase_keys(x.0.keys().filter_map(|k| match k {
+                    AstItem::ValueStr(k) => Some(k.0.as_ref()),
+                    _ => None,
+                }));
+                let entries = x
+                    .0
+                    .into_iter()
+                    .map(|(k, v)| match k {
+                        AstItem::ValueStr(k) => match lowercase.get(k.0.as_ref()) {
+                            Some(Some(lower)) => (ValueStr(lower.clone().into(), k.1).into(), v),
+                            _ => (k.into(), v),
+                        },
+                        k => (k, v),
+                    })
+                    .collect();
+                ItemMap(entries).into()
+            }
+            (_, item) => item,
+        }
+    }
+}
+
+impl ValueKind {
+    fn matches(self, item: &AstItem) -> bool {
+        static UUID: OnceLock<Regex> = OnceLock::new();
+        static TIMESTAMP: OnceLock<Regex> = OnceLock::new();
+
+        match (self, item) {
+            (ValueKind::Pointer, AstItem::ValueOpaque(x)) => x.0 == OpaqueKind::Pointer,
+            (ValueKind::Uuid, AstItem::ValueStr(x)) => UUID
+                .get_or_init(|| {
+                    Regex::new("^[0-9a-fA-F]{8}-([0-9a-fA-F]{4}-){3}[0-9a-fA-F]{12}$").unwrap()
+                })
+                .is_match(&x.unescape()),
+            (ValueKind::Timestamp, AstItem::ValueStr(x)) => TIMESTAMP
+                .get_or_init(|| {
+                    Regex::new(
+                        r"^\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?$",
+                    )
+                    .unwrap()
+                })
+                .is_match(&x.unescape()),
+            (ValueKind::Timestamp, AstItem::ItemStruct(x)) => {
+                matches!(x.0.as_ref(), "SystemTime" | "Instant")
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The lowercase version of each key, or `None` for the keys which would then collide with another.
+fn lowercase_keys<'k>(keys: impl Iterator<Item = &'k str>) -> HashMap<String, Option<String>> {
+    let keys = keys.map(|k| (k, k.to_lowercase())).collect::<Vec<_>>();
+    let mut counts = HashMap::<&str, usize>::new();
+    for (_, lower) in &keys {
+        *counts.entry(lower).or_default() += 1;
+    }
+
+    keys.iter()
+        .map(|(k, lower)| {
+            (
+                k.to_string(),
+                (counts[lower.as_str()] == 1).then(|| lower.clone()),
+            )
+        })
+        .collect()
+}
+
+/// The text a `match` is looked for in, for values without children.
+fn leaf_text<'r>(item: &'r AstItem) -> Option<Cow<'r, str>> {
+    match item {
+        AstItem::ItemUnitStruct(x) => Some(Cow::Borrowed(&x.0)),
+        AstItem::ValueStr(x) => Some(x.unescape()),
+        AstItem::ValueBool(_)
+        | AstItem::ValueBytes(_)
+        | AstItem::ValueDuration(_)
+        | AstItem::ValueIpAddr(_)
+        | AstItem::ValueNum(_)
+        | AstItem::ValueOpaque(_)
+        | AstItem::ValueSocketAddr(_) => Some(Cow::Owned(item.to_string())),
+        _ => None,
+    }
+}
+
+struct Normalize<'r>(&'r Rules);
+
+impl<'a> Fold<'a> for Normalize<'_> {
+    fn fold_item(&mut self, path: &mut DiffPath<'a, 'a>, item: AstItem<'a>) -> AstItem<'a> {
+        let mut item = match item {
+            // Rewritten elements could become equal, and the set would lose some of them.
+            AstItem::ItemSet(_) => item,
+            item => walk_item_fold(self, path, item),
+        };
+        for rule in &self.0 .0 {
+            if rule.selects(path, &item) {
+                item = rule.apply(item);
+            }
+        }
+
+        item
+    }
+}
+
+/// Why a list of rules is invalid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RulesError(String);
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid rules: {}", self.0)
+    }
+}
+
+impl error::Error for RulesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    /// Apply the rules to `input` and check the result is `expected`.
+    fn check(rules: &str, input: &str, expected: &str) {
+        let rules = Rules::from_yaml(rules).unwrap();
+        let mut item = parse(input).unwrap();
+        rules.apply(&mut item);
+        assert_eq!(item, parse(expected).unwrap(), "{input}");
+    }
+
+    #[test]
+    fn placeholders() {
+        let rules = "
+            - kind: uuid
+              placeholder: uuid
+            - kind: timestamp
+              placeholder: <time>
+            - kind: pointer
+              placeholder: ptr
+        ";
+        check(
+            rules,
+            r#"Job { id: "67e55044-10b1-426f-9247-bb680e5fe0c8", at: "2024-05-01T10:00:00Z", f: 0x7ffd5a3c }"#,
+            "Job { id: <uuid>, at: <time>, f: <ptr> }",
+        );
+        check(rules, "SystemTime { tv_sec: 1, tv_nsec: 2 }", "<time>");
+        check(rules, r#"["not a uuid", 12]"#, r#"["not a uuid", 12]"#);
+        // Keys are left alone.
+        check(
+            rules,
+            r#"{"67e55044-10b1-426f-9247-bb680e5fe0c8": 1}"#,
+            r#"{"67e55044-10b1-426f-9247-bb680e5fe0c8": 1}"#,
+        );
+        // So are the elements of sets, which would otherwise merge.
+        let set =
+            r#"{"67e55044-10b1-426f-9247-bb680e5fe0c8", "67e55044-10b1-426f-9247-bb680e5fe0c9"}"#;
+        check(rules, set, set);
+        check(
+            rules,
+            &format!("Job {{ ids: {set}, at: \"2024-05-01T10:00:00Z\" }}"),
+            &format!("Job {{ ids: {set}, at: <time> }}"),
+        );
+    }
+
+    #[test]
+    fn paths_and_matches() {
+        let rules = "
+            - path: .users[*].token
+              placeholder: token
+            - match: ^tmp
+              placeholder: temp
+        ";
+        check(
+            rules,
+            r#"Conf { users: [User { token: "a" }, User { token: "b" }], token: "c", dir: tmp_1, n: 12 }"#,
+            r#"Conf { users: [User { token: <token> }, User { token: <token> }], token: "c", dir: <temp>, n: 12 }"#,
+        );
+    }
+
+    #[test]
+    fn replace() {
+        check(
+            r#"
+            - match: "/tmp/([a-z]+)[0-9]+"
+              replace: /tmp/$1
+            "#,
+            r#"["/tmp/run42/a", "/tmp/x1 /tmp/y2", "/var/tmp", 42]"#,
+            r#"["/tmp/run/a", "/tmp/x /tmp/y", "/var/tmp", 42]"#,
+        );
+    }
+
+    #[test]
+    fn round() {
+        check(
+            "- round: 2",
+            "[1.23456, 12, -2.5, 3.0, Point { x: 0.1001 }]",
+            "[1.23, 12, -2.50, 3.00, Point { x: 0.10 }]",
+        );
+    }
+
+    #[test]
+    fn lowercase_keys() {
+        let rules = "- lowercase_keys: true";
+        check(
+            rules,
+            r#"Headers { Host: "a", map: {"Content-Type": "b", 1: "c"} }"#,
+            r#"Headers { host: "a", map: {"content-type": "b", 1: "c"} }"#,
+        );
+        // Keys which would collide are kept, so that no value is lost.
+        check(
+            rules,
+            r#"{"Accept": 1, "accept": 2, "Host": 3}"#,
+            r#"{"Accept": 1, "accept": 2, "host": 3}"#,
+        );
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for rules in [
+            "- placeholder: a\n  round: 2",
+            "- path: .a",
+            "- replace: b",
+            "- placeholder: <>",
+            "- path: '.a['\n  placeholder: a",
+            "- match: '('\n  placeholder: a",
+            "- kind: closure\n  placeholder: a",
+            "- colour: red\n  placeholder: a",
+        ] {
+            assert!(Rules::from_yaml(rules).is_err(), "{rules}");
+        }
+    }
+}
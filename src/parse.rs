@@ -1,12 +1,153 @@
+//! Parsing of `Debug` output (and of JSON and RON) into trees.
+
+pub(crate) use self::{json::json_parser, ron::ron_parser};
 use crate::ast::*;
 use chumsky::{extra::Err, prelude::*, text::keyword};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    error, fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+    ops::Range,
+    str::FromStr,
+};
+
+mod json;
+mod ron;
+
+pub(crate) type Spanned<T> = (T, SimpleSpan);
+
+/// Why some text couldn't be parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    span: Range<usize>,
+    message: String,
+}
+
+impl ParseError {
+    /// The byte range of the input where parsing failed.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// What was expected at that point, and what was found instead.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Parse a value printed using `Debug` (either `{:?}` or `{:#?}`).
+pub fn parse(text: &str) -> Result<AstItem<'_>, ParseError> {
+    run(parser(), text)
+}
+
+/// Parse a JSON value: objects become structs without a name, arrays lists and `null` `None`.
+pub fn parse_json(text: &str) -> Result<AstItem<'_>, ParseError> {
+    run(json_parser(), text)
+}
+
+/// Parse a RON value. Characters become strings, and identifiers unit structs.
+pub fn parse_ron(text: &str) -> Result<AstItem<'_>, ParseError> {
+    run(ron_parser(), text)
+}
+
+/// Parse a sequence of values separated by whitespace, returning each one along with its byte
+/// range.
+///
+/// When `skip_text` is set, lines that don't hold a value are ignored, and values must start at the
+/// beginning of a line and end at the end of one.
+pub fn parse_document(
+    text: &str,
+    skip_text: bool,
+) -> Result<Vec<(AstItem<'_>, Range<usize>)>, ParseError> {
+    let values = run(document_parser(skip_text), text)?;
+    Ok(values
+        .into_iter()
+        .map(|(value, span)| (value, span.into_range()))
+        .collect())
+}
 
-pub fn parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>> {
+/// Run a parser to completion, keeping the first error.
+pub(crate) fn run<'a, T>(
+    parser: impl Parser<'a, &'a str, T, Err<Rich<'a, char>>>,
+    text: &'a str,
+) -> Result<T, ParseError> {
+    parser.parse(text).into_result().map_err(|errors| {
+        let error = &errors[0];
+        ParseError {
+            span: error.span().into_range(),
+            message: error.reason().to_string(),
+        }
+    })
+}
+
+pub(crate) fn parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>> {
     ast_parser().padded().then_ignore(end())
 }
 
-fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>> {
+/// Parse a sequence of values separated by whitespace, like a log of one snapshot per step.
+///
+/// When `skip_text` is set, lines that don't hold a value (headers, messages...) are ignored. A value
+/// must then start at the beginning of a line and end at the end of one, but may span multiple lines
+/// in between.
+pub(crate) fn document_parser<'a>(
+    skip_text: bool,
+) -> impl Parser<'a, &'a str, Vec<Spanned<AstItem<'a>>>, Err<Rich<'a, char>>> {
+    let value = ast_parser().map_with_span(|x, span| (x, span));
+    let entry = if skip_text {
+        let line_end = one_of(" \t")
+            .repeated()
+            .then(text::newline().or(end()))
+            .rewind();
+        let text_line = none_of("\n").repeated().at_least(1).to(None);
+
+        value.then_ignore(line_end).map(Some).or(text_line).boxed()
+    } else {
+        value.map(Some).boxed()
+    };
+
+    entry
+        .padded()
+        .repeated()
+        .collect::<Vec<_>>()
+        .then_ignore(end())
+        .map(|entries| entries.into_iter().flatten().collect())
+}
+
+/// Parse a single value at the start of the input, returning it along with its span and ignoring
+/// whatever follows it.
+///
+/// A parser has to reach the end of its input to succeed, and skipping the rest of the text would
+/// make scanning it for values quadratic. The value is instead taken out as soon as it's parsed,
+/// and parsing then stops at the next character, failing there unless it's the end.
+pub(crate) fn parse_prefix(text: &str) -> Option<Spanned<AstItem<'_>>> {
+    let found = RefCell::new(None);
+    let _ = ast_parser()
+        .map_with(|x, e| *found.borrow_mut() = Some((x, e.span())))
+        .then_ignore(end())
+        .parse(text);
+    found.into_inner()
+}
+
+pub(crate) fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>> + Clone
+{
     recursive(|value| {
+        let item_empty = just('{')
+            .then(text::whitespace())
+            .then(just('}'))
+            .to(ItemEmpty);
         let item_list = value
             .clone()
             .separated_by(just(',').padded())
@@ -21,6 +162,7 @@ fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>
             .then(value.clone())
             .separated_by(just(',').padded())
             .allow_trailing()
+            .at_least(1)
             .collect()
             .padded()
             .delimited_by(just('{'), just('}'))
@@ -28,7 +170,15 @@ fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>
         let item_option = keyword("None")
             .to(None)
             .or(keyword("Some")
-                .ignore_then(value.clone().padded().delimited_by(just('('), just(')')))
+                .ignore_then(
+                    // `{:#?}` adds a trailing comma (`Some(\n    1,\n)`).
+                    value
+                        .clone()
+                        .padded()
+                        .then_ignore(just(',').or_not())
+                        .padded()
+                        .delimited_by(just('('), just(')')),
+                )
                 .map(Box::new)
                 .map(Some))
             .map(ItemOption);
@@ -36,6 +186,7 @@ fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>
             .clone()
             .separated_by(just(',').padded())
             .allow_trailing()
+            .at_least(1)
             .collect()
             .padded()
             .delimited_by(just('{'), just('}'))
@@ -44,15 +195,36 @@ fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>
             .then_ignore(text::whitespace())
             .then(
                 text::ident()
+                    .map(Cow::Borrowed)
                     .then_ignore(just(':').padded())
                     .then(value.clone())
                     .separated_by(just(',').padded())
                     .allow_trailing()
                     .collect()
+                    .then(just("..").padded().or_not().map(|x| x.is_some()))
                     .padded()
                     .delimited_by(just('{'), just('}')),
             )
-            .map(|(name, items)| ItemStruct(name, items));
+            .map(|(name, (items, non_exhaustive)): (&str, _)| {
+                ItemStruct(name.into(), items, non_exhaustive)
+            });
+        // `debug_struct("")` prints a struct without a name (`{ a: 1, .. }`), which would read as
+        // a map keyed by unit structs, or not at all. Unit structs are named in `CamelCase`, so
+        // braces holding only `snake_case` keys are read as such a struct.
+        let field_name = text::ident().filter(|x: &&str| {
+            x.starts_with(|x: char| x.is_lowercase() || x == '_') && !matches!(*x, "true" | "false")
+        });
+        let item_anonymous_struct = field_name
+            .map(Cow::Borrowed)
+            .then_ignore(just(':').padded())
+            .then(value.clone())
+            .separated_by(just(',').padded())
+            .allow_trailing()
+            .collect()
+            .then(just("..").padded().or_not().map(|x| x.is_some()))
+            .padded()
+            .delimited_by(just('{'), just('}'))
+            .map(|(items, non_exhaustive)| ItemStruct("".into(), items, non_exhaustive));
         let item_tuple = value
             .clone()
             .separated_by(just(',').padded())
@@ -71,34 +243,116 @@ fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>
                     .padded()
                     .delimited_by(just('('), just(')')),
             )
-            .map(|(name, items)| ItemTupleStruct(name, items));
-        let item_unit_struct = text::ident().map(ItemUnitStruct);
+            .map(|(name, items): (&str, _)| ItemTupleStruct(name.into(), items));
+        let item_unit_struct = text::ident().map(|x: &str| ItemUnitStruct(x.into()));
 
         let value_bool = text::keyword("true")
             .to(true)
             .or(text::keyword("false").to(false))
             .map(ValueBool);
-        let value_num = just('-')
-            .or_not()
-            .then(
-                text::digits(10)
-                    .repeated()
-                    .at_least(1)
-                    .then(just('.').then(text::digits(10).repeated()).or_not()),
+        let value_bytes = just('b').ignore_then(
+            escaped_chars()
+                .map_slice(|x: &str| ValueBytes(x.into()))
+                .delimited_by(just('"'), just('"')),
+        );
+        let value_duration = text::digits(10)
+            .repeated()
+            .at_least(1)
+            .then(just('.').then(text::digits(10).repeated()).or_not())
+            .then(choice((just("ns"), just("µs"), just("ms"), just("s"))))
+            .then_ignore(
+                any()
+                    .filter(|x: &char| x.is_alphanumeric() || *x == '_')
+                    .not(),
             )
-            .map_slice(ValueNum);
-        let value_str = none_of("\\\"")
-            .ignored()
-            .or(just('\\').ignore_then(any()).ignored())
+            .map_slice(|x: &str| ValueDuration(x.into()));
+        let ipv4 = text::digits(10)
             .repeated()
-            .map_slice(ValueStr)
+            .at_least(1)
+            .separated_by(just('.'))
+            .exactly(4)
+            .map_slice(|x: &str| x)
+            .try_map(validate_slice::<Ipv4Addr>);
+        // Stop before `: ` and `..`, so that an address can be a map key or a range bound.
+        let ipv6 = choice((
+            one_of("0123456789abcdefABCDEF").ignored(),
+            just(':').then(just(' ').not()).ignored(),
+            just('.').then(just('.').not()).ignored(),
+        ))
+        .repeated()
+        .at_least(2)
+        .map_slice(|x: &str| x)
+        .try_map(validate_slice::<Ipv6Addr>);
+        let value_ip_addr = ipv4.or(ipv6).map(|x: &str| ValueIpAddr(x.into()));
+        let port = just(':').then(text::digits(10).repeated().at_least(1));
+        let value_socket_addr = choice((
+            ipv4.then(port).ignored(),
+            // A scoped address (`[fe80::1%3]:80`) only ever appears within a socket address.
+            ipv6.then(
+                just('%')
+                    .then(text::digits(10).repeated().at_least(1))
+                    .or_not(),
+            )
+            .delimited_by(just('['), just(']'))
+            .then(port)
+            .ignored(),
+        ))
+        .map_slice(|x: &str| ValueSocketAddr(x.into()));
+        // `NaN` and `inf` can't be told apart from unit structs, but `-inf` can.
+        let value_num = just("-inf")
+            .ignored()
+            .or(just('-')
+                .or_not()
+                .then(text::digits(10).repeated().at_least(1))
+                .then(
+                    // Don't mistake the start of a range (`0..10`) for a decimal point.
+                    just('.')
+                        .then_ignore(just('.').not())
+                        .then(text::digits(10).repeated())
+                        .or_not(),
+                )
+                .then(
+                    just('e')
+                        .then(just('-').or_not())
+                        .then(text::digits(10).repeated().at_least(1))
+                        .or_not(),
+                )
+                .ignored())
+            .map_slice(|x: &str| ValueNum(x.into()));
+        let closure_name = choice((just("closure"), just("async block"), just("async fn body")))
+            .then(just('#').then(text::digits(10)).or_not());
+        let value_opaque = choice((
+            just("0x")
+                .then(one_of("0123456789abcdefABCDEF").repeated().at_least(1))
+                .map_slice(|x: &str| ValueOpaque(OpaqueKind::Pointer, x.into())),
+            just('<')
+                .then(none_of("<>\n").repeated().at_least(1))
+                .then(just('>'))
+                .map_slice(|x: &str| ValueOpaque(OpaqueKind::Placeholder, x.into())),
+            choice((
+                closure_name.delimited_by(just("{{"), just("}}")),
+                closure_name.delimited_by(just('{'), just('}')),
+            ))
+            .map_slice(|x: &str| ValueOpaque(OpaqueKind::Closure, x.into())),
+        ));
+        let value_str = escaped_chars()
+            .map_slice(|x: &str| ValueStr(x.into(), StrKind::Escaped))
             .delimited_by(just('"'), just('"'));
+        let value_raw_str = raw_str_parser();
 
-        choice((
+        let atom = choice((
+            item_empty.map(AstItem::from),
+            value_opaque.map(AstItem::from),
+            value_socket_addr.map(AstItem::from),
+            value_ip_addr.map(AstItem::from),
+            value_duration.map(AstItem::from),
             item_list.map(AstItem::from),
+            item_anonymous_struct.map(AstItem::from),
             item_map.map(AstItem::from),
             item_option.map(AstItem::from),
             item_set.map(AstItem::from),
+            value_bytes.map(AstItem::from),
+            value_raw_str.map(AstItem::from),
             item_struct.map(AstItem::from),
             item_tuple.map(AstItem::from),
             item_tuple_struct.map(AstItem::from),
@@ -107,5 +361,302 @@ fn ast_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>
             value_num.map(AstItem::from),
             value_str.map(AstItem::from),
         ))
+        .boxed();
+
+        let range_limits = just("..=")
+            .to(RangeLimits::Closed)
+            .or(just("..").to(RangeLimits::HalfOpen));
+        let item_range = range_limits
+            .then(atom.clone().or_not())
+            .map(|(limits, end)| ItemRange(None, limits, end.map(Box::new)));
+
+        atom.clone()
+            .then(range_limits.then(atom.or_not()).or_not())
+            .map(|(start, range)| match range {
+                Some((limits, end)) => {
+                    ItemRange(Some(Box::new(start)), limits, end.map(Box::new)).into()
+                }
+                None => start,
+            })
+            .or(item_range.map(AstItem::from))
     })
 }
+
+/// Accept the slice only if it's a valid `T`.
+fn validate_slice<'a, T>(x: &'a str, span: SimpleSpan) -> Result<&'a str, Rich<'a, char>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match x.parse::<T>() {
+        Ok(_) => Ok(x),
+        Err(e) => Err(Rich::custom(span, e)),
+    }
+}
+
+/// The contents of a string literal, escape sequences included.
+fn escaped_chars<'a>() -> impl Parser<'a, &'a str, (), Err<Rich<'a, char>>> + Clone {
+    none_of("\\\"")
+        .ignored()
+        .or(just('\\').ignore_then(any()).ignored())
+        .repeated()
+}
+
+/// A raw string (`r#"..."#`), with any number of hashes.
+///
+/// `Debug` never emits raw strings by itself, but hand-written implementations sometimes do.
+fn raw_str_parser<'a>() -> impl Parser<'a, &'a str, ValueStr<'a>, Err<Rich<'a, char>>> + Clone {
+    // The body is parsed knowing the number of hashes, which it gets as its context.
+    let hashes = just('#')
+        .repeated()
+        .configure(|cfg, ctx: &usize| cfg.exactly(*ctx));
+    let fence = just('"').then(hashes.count());
+    let body = any()
+        .and_is(fence.not())
+        .repeated()
+        .map_slice(|x: &str| x)
+        .then_ignore(fence);
+
+    just('r')
+        .ignore_then(just('#').repeated().count())
+        .then_ignore(just('"'))
+        .then_with_ctx(body)
+        .map(|(hashes, x)| ValueStr(x.into(), StrKind::Raw(hashes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::strategy::ast_item;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn display_round_trips(item in ast_item()) {
+            let text = item.to_string();
+            prop_assert_eq!(parser().parse(&text).into_result(), Ok(item));
+        }
+
+        #[test]
+        fn pretty_round_trips(
+            item in ast_item(),
+            indent in 0..8usize,
+            width in proptest::option::of(0..120usize),
+            sort: bool,
+        ) {
+            let config = PrettyConfig { indent, width, sort };
+            let text = Pretty(&item, &config).to_string();
+            prop_assert_eq!(parser().parse(&text).into_result(), Ok(item));
+        }
+    }
+
+    #[test]
+    fn pretty_layout() {
+        let item = parse("A { ids: {10, 9}, tags: [\"a\"], m: {10: (), 9: B(1)} }").unwrap();
+        let config = PrettyConfig::default();
+        assert_eq!(Pretty(&item, &config).to_string(), format!("{item:#}"));
+
+        let config = PrettyConfig {
+            indent: 2,
+            width: Some(20),
+            sort: true,
+        };
+        assert_eq!(
+            Pretty(&item, &config).to_string(),
+            "A {\n  ids: {9, 10},\n  m: {\n    9: B(1),\n    10: (),\n  },\n  tags: [\"a\"],\n}"
+        );
+
+        let config = PrettyConfig {
+            width: Some(80),
+            ..config
+        };
+        assert_eq!(
+            Pretty(&item, &config).to_string(),
+            "A { ids: {9, 10}, m: {9: B(1), 10: ()}, tags: [\"a\"] }"
+        );
+        assert_eq!(format!("{item:+}"), item.to_string());
+    }
+
+    #[test]
+    fn braces() {
+        let fields = |x: &[(&'static str, AstItem<'static>)]| {
+            x.iter()
+                .map(|(k, v)| (Cow::Borrowed(*k), v.clone()))
+                .collect()
+        };
+        let one = || AstItem::from(ValueNum("1".into()));
+
+        // Empty maps and sets can't be told apart.
+        assert_eq!(parse("{}"), Ok(ItemEmpty.into()));
+        assert_eq!(parse("{ \n }"), Ok(ItemEmpty.into()));
+        assert_eq!(
+            parse("Foo {}"),
+            Ok(ItemStruct("Foo".into(), fields(&[]), false).into())
+        );
+        assert_eq!(
+            parse("Foo { .. }"),
+            Ok(ItemStruct("Foo".into(), fields(&[]), true).into())
+        );
+        assert_eq!(
+            parse("Foo { a: {} }"),
+            Ok(ItemStruct("Foo".into(), fields(&[("a", ItemEmpty.into())]), false).into())
+        );
+
+        // Structs without a name.
+        assert_eq!(
+            parse("{ a: 1 }"),
+            Ok(ItemStruct("".into(), fields(&[("a", one())]), false).into())
+        );
+        assert_eq!(
+            parse("{ a: 1, _b: {}, .. }"),
+            Ok(ItemStruct(
+                "".into(),
+                fields(&[("a", one()), ("_b", ItemEmpty.into())]),
+                true
+            )
+            .into())
+        );
+        assert_eq!(
+            parse("{ .. }"),
+            Ok(ItemStruct("".into(), fields(&[]), true).into())
+        );
+
+        // Maps and sets.
+        assert!(matches!(parse("{Foo: 1}"), Ok(AstItem::ItemMap(_))));
+        assert!(matches!(parse("{true: 1}"), Ok(AstItem::ItemMap(_))));
+        assert!(matches!(parse("{a: 1, Foo: 1}"), Ok(AstItem::ItemMap(_))));
+        assert!(matches!(parse("{a, b}"), Ok(AstItem::ItemSet(_))));
+        assert!(matches!(parse("{..5}"), Ok(AstItem::ItemSet(_))));
+    }
+
+    #[test]
+    fn ranges() {
+        let num = |x: &'static str| Some(Box::new(AstItem::from(ValueNum(x.into()))));
+        let range = |start, limits, end| Ok(ItemRange(start, limits, end).into());
+        assert_eq!(
+            parse("0..10"),
+            range(num("0"), RangeLimits::HalfOpen, num("10"))
+        );
+        assert_eq!(
+            parse("-1..=5"),
+            range(num("-1"), RangeLimits::Closed, num("5"))
+        );
+        assert_eq!(
+            parse("2.5.."),
+            range(num("2.5"), RangeLimits::HalfOpen, None)
+        );
+        assert_eq!(parse("..=3"), range(None, RangeLimits::Closed, num("3")));
+        assert_eq!(parse(".."), range(None, RangeLimits::HalfOpen, None));
+        assert!(matches!(
+            parse(r#"[1s..2s, "a".."b", A..B]"#),
+            Ok(AstItem::ItemList(ItemList(x))) if x.iter().all(|x| matches!(x, AstItem::ItemRange(_)))
+        ));
+        assert!(parse("1...2").is_err());
+    }
+
+    #[test]
+    fn byte_strings() {
+        let bytes = |text| match parse(text) {
+            Ok(AstItem::ValueBytes(x)) => x.to_bytes(),
+            x => panic!("{text}: {x:?}"),
+        };
+        assert_eq!(bytes(r#"b"""#), b"");
+        assert_eq!(bytes(r#"b"\x00abc\xff""#), b"\0abc\xff");
+        assert_eq!(bytes(r#"b"\n\t\\\"\'""#), b"\n\t\\\"'");
+        assert!(parse(r#"b"abc"#).is_err());
+    }
+
+    #[test]
+    fn raw_strings() {
+        let raw = |text, contents, hashes| {
+            assert_eq!(
+                parse(text),
+                Ok(ValueStr(Cow::Borrowed(contents), StrKind::Raw(hashes)).into()),
+                "{text}"
+            );
+        };
+        raw(r#"r"a\b""#, r"a\b", 0);
+        raw(r##"r#"say "hi""#"##, r#"say "hi""#, 1);
+        raw(r###"r##"a"#b"##"###, r##"a"#b"##, 2);
+        assert!(parse(r##"r#"a""##).is_err());
+        assert!(parse(r###"r#"a"##"###).is_err());
+
+        assert_eq!(ValueStr(r"a\b".into(), StrKind::Raw(0)).unescape(), r"a\b");
+    }
+
+    #[test]
+    fn documents() {
+        let text = "step 1\nA { x: 1 }\nstep 2: done\nA {\n    x: 2,\n}\n[1]\n";
+        let values = parse_document(text, true).unwrap();
+        let values = values
+            .iter()
+            .map(|(x, span)| (x.to_string(), &text[span.clone()]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("A { x: 1 }".to_owned(), "A { x: 1 }"),
+                ("A { x: 2 }".to_owned(), "A {\n    x: 2,\n}"),
+                ("[1]".to_owned(), "[1]"),
+            ]
+        );
+
+        assert_eq!(parse_document(" 1 2\n\n3 ", false).unwrap().len(), 3);
+        assert!(parse_document("1 \"two", false).is_err());
+        // Values must fill whole lines to be told apart from text.
+        assert_eq!(parse_document("x = 1\n2 apples\n", true).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn addresses() {
+        for text in ["::", "::1", "1::", "fe80::1", "::ffff:1.2.3.4", "127.0.0.1"] {
+            assert_eq!(parse(text), Ok(ValueIpAddr(text.into()).into()), "{text}");
+        }
+        for text in [
+            "[::1]:80",
+            "[::ffff:1.2.3.4]:443",
+            "[fe80::1%3]:8080",
+            "10.0.0.1:0",
+        ] {
+            let item = parse(text).unwrap();
+            assert_eq!(item, ValueSocketAddr(text.into()).into(), "{text}");
+            let AstItem::ValueSocketAddr(x) = item else {
+                unreachable!()
+            };
+            assert!(x.to_socket_addr().is_some(), "{text}");
+        }
+
+        // Addresses stop before the separator of a map entry or a range.
+        let ip = |x: &'static str| Box::new(AstItem::from(ValueIpAddr(x.into())));
+        assert_eq!(
+            parse("{::1: 5, fe80::1: 6}"),
+            Ok(ItemMap(std::collections::BTreeMap::from([
+                (*ip("::1"), ValueNum("5".into()).into()),
+                (*ip("fe80::1"), ValueNum("6".into()).into()),
+            ]))
+            .into())
+        );
+        assert_eq!(
+            parse("::1..::ffff:1.2.3.4"),
+            Ok(ItemRange(
+                Some(ip("::1")),
+                RangeLimits::HalfOpen,
+                Some(ip("::ffff:1.2.3.4"))
+            )
+            .into())
+        );
+        assert_eq!(
+            parse("::ffff:1.2.3.4..=1::"),
+            Ok(ItemRange(
+                Some(ip("::ffff:1.2.3.4")),
+                RangeLimits::Closed,
+                Some(ip("1::"))
+            )
+            .into())
+        );
+
+        // Numbers with a dot aren't addresses.
+        assert_eq!(parse("1.2"), Ok(ValueNum("1.2".into()).into()));
+        assert!(parse("1.2.3").is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! JSON front-end.
+//!
+//! Objects become structs without a name, which the diff compares against structs of any name (see
+//! [`DiffConfig::cross_format`](crate::diff::DiffConfig::cross_format)). Everything else maps onto
+//! the closest value: `null` is `None`, arrays are lists, and strings and numbers keep their text.
+
+use super::escaped_chars;
+use crate::ast::*;
+use chumsky::{extra::Err, prelude::*};
+use std::borrow::Cow;
+
+pub(crate) fn json_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>> {
+    recursive(|value| {
+        let string = escaped_chars()
+            .map_slice(|x: &str| x)
+            .delimited_by(just('"'), just('"'));
+
+        let array = value
+            .clone()
+            .separated_by(just(',').padded())
+            .collect()
+            .padded()
+            .delimited_by(just('['), just(']'))
+            .map(ItemList);
+        let object = string
+            .clone()
+            .map(Cow::Borrowed)
+            .then_ignore(just(':').padded())
+            .then(value)
+            .separated_by(just(',').padded())
+            .collect()
+            .padded()
+            .delimited_by(just('{'), just('}'))
+            .map(|fields| ItemStruct("".into(), fields, false));
+        let number = just('-')
+            .or_not()
+            .then(text::int(10))
+            .then(
+                just('.')
+                    .then(text::digits(10).repeated().at_least(1))
+                    .or_not(),
+            )
+            .then(
+                one_of("eE")
+                    .then(one_of("+-").or_not())
+                    .then(text::digits(10).repeated().at_least(1))
+                    .or_not(),
+            )
+            .map_slice(|x: &str| ValueNum(x.into()));
+
+        choice((
+            text::keyword("null").to(ItemOption(None).into()),
+            text::keyword("true").to(ValueBool(true).into()),
+            text::keyword("false").to(ValueBool(false).into()),
+            number.map(AstItem::from),
+            string
+                .map(|x| ValueStr(x.into(), StrKind::Escaped))
+                .map(AstItem::from),
+            array.map(AstItem::from),
+            object.map(AstItem::from),
+        ))
+    })
+    .padded()
+    .then_ignore(end())
+}
@@ -0,0 +1,153 @@
+//! RON front-end.
+//!
+//! RON maps almost one to one onto the values printed by `Debug`: `Foo(a: 1)` is a struct,
+//! `Foo(1)` a tuple struct and `Foo` a unit struct. Structs without a name (`(a: 1)`) are compared
+//! against structs of any name (see
+//! [`DiffConfig::cross_format`](crate::diff::DiffConfig::cross_format)), and characters become
+//! strings.
+
+use super::{escaped_chars, raw_str_parser};
+use crate::ast::*;
+use chumsky::{extra::Err, prelude::*, text::keyword};
+use std::borrow::Cow;
+
+pub(crate) fn ron_parser<'a>() -> impl Parser<'a, &'a str, AstItem<'a>, Err<Rich<'a, char>>> {
+    let comment = just("//")
+        .then(none_of("\n").repeated())
+        .ignored()
+        .or(just("/*")
+            .then(any().and_is(just("*/").not()).repeated())
+            .then(just("*/"))
+            .ignored());
+    let ws = comment
+        .or(any().filter(|x: &char| x.is_whitespace()).ignored())
+        .repeated();
+    let extension = just("#![")
+        .then(none_of("]").repeated())
+        .then(just(']'))
+        .padded_by(ws);
+
+    let value = recursive(|value| {
+        let items = value
+            .clone()
+            .separated_by(just(',').padded_by(ws))
+            .allow_trailing()
+            .collect::<Vec<_>>()
+            .padded_by(ws);
+        let fields = text::ident()
+            .map(Cow::Borrowed)
+            .then_ignore(just(':').padded_by(ws))
+            .then(value.clone())
+            .separated_by(just(',').padded_by(ws))
+            .allow_trailing()
+            .at_least(1)
+            .collect()
+            .padded_by(ws);
+
+        let item_list = items
+            .clone()
+            .delimited_by(just('['), just(']'))
+            .map(ItemList);
+        let item_map = value
+            .clone()
+            .then_ignore(just(':').padded_by(ws))
+            .then(value.clone())
+            .separated_by(just(',').padded_by(ws))
+            .allow_trailing()
+            .collect()
+            .padded_by(ws)
+            .delimited_by(just('{'), just('}'))
+            .map(ItemMap);
+        let item_option = keyword("None").to(None).or(keyword("Some")
+            .ignore_then(
+                value
+                    .clone()
+                    .padded_by(ws)
+                    .then_ignore(just(',').or_not())
+                    .padded_by(ws)
+                    .delimited_by(just('('), just(')')),
+            )
+            .map(Box::new)
+            .map(Some));
+        let item_struct = text::ident()
+            .or_not()
+            .then_ignore(ws)
+            .then(fields.delimited_by(just('('), just(')')))
+            .map(|(name, fields): (Option<&str>, _)| {
+                ItemStruct(name.unwrap_or_default().into(), fields, false)
+            });
+        let item_tuple_struct = text::ident()
+            .then_ignore(ws)
+            .then(items.clone().delimited_by(just('('), just(')')))
+            .map(|(name, items): (&str, _)| ItemTupleStruct(name.into(), items));
+        let item_tuple = items.delimited_by(just('('), just(')')).map(ItemTuple);
+
+        let value_num = one_of("+-")
+            .or_not()
+            .then(choice((
+                just("0x")
+                    .then(one_of("0123456789abcdefABCDEF_").repeated().at_least(1))
+                    .ignored(),
+                just("0b")
+                    .then(one_of("01_").repeated().at_least(1))
+                    .ignored(),
+                just("0o")
+                    .then(one_of("01234567_").repeated().at_least(1))
+                    .ignored(),
+                one_of("0123456789_")
+                    .repeated()
+                    .at_least(1)
+                    .then(just('.').then(one_of("0123456789_").repeated()).or_not())
+                    .then(
+                        one_of("eE")
+                            .then(one_of("+-").or_not())
+                            .then(text::digits(10).repeated().at_least(1))
+                            .or_not(),
+                    )
+                    .ignored(),
+                keyword("inf").ignored(),
+                keyword("NaN").ignored(),
+            )))
+            .map_slice(|x: &str| ValueNum(x.into()));
+        let value_bytes = just('b').ignore_then(
+            escaped_chars()
+                .map_slice(|x: &str| ValueBytes(x.into()))
+                .delimited_by(just('"'), just('"')),
+        );
+        let value_char = just('\\')
+            .then(any())
+            .ignored()
+            .or(none_of("\\'").ignored())
+            .repeated()
+            .at_least(1)
+            .map_slice(|x: &str| ValueStr(x.into(), StrKind::Escaped))
+            .delimited_by(just('\''), just('\''));
+        let value_str = escaped_chars()
+            .map_slice(|x: &str| ValueStr(x.into(), StrKind::Escaped))
+            .delimited_by(just('"'), just('"'));
+        let value_raw_str = raw_str_parser();
+
+        choice((
+            item_option.map(|x| ItemOption(x).into()),
+            keyword("true").to(ValueBool(true).into()),
+            keyword("false").to(ValueBool(false).into()),
+            value_bytes.map(AstItem::from),
+            value_raw_str.map(AstItem::from),
+            item_struct.map(AstItem::from),
+            item_tuple_struct.map(AstItem::from),
+            item_tuple.map(AstItem::from),
+            item_list.map(AstItem::from),
+            item_map.map(AstItem::from),
+            value_num.map(AstItem::from),
+            value_char.map(AstItem::from),
+            value_str.map(AstItem::from),
+            text::ident().map(|x: &str| ItemUnitStruct(x.into()).into()),
+        ))
+        .boxed()
+    });
+
+    extension
+        .repeated()
+        .ignore_then(value.padded_by(ws))
+        .then_ignore(end())
+}
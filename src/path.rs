@@ -0,0 +1,304 @@
+//! Patterns selecting parts of a value by their path, like `.items[*].id`.
+//!
+//! A pattern is a sequence of segments, each one matching a single step of a [`DiffPath`]:
+//!
+//! - `.name` matches a struct field (or the `start` or `end` of a range).
+//! - `[2]` matches the item at that index in a list, tuple or set.
+//! - `["key"]`, `[42]` match the entry of a map with that key, written the way it's printed.
+//! - `[id=42]` matches the item of a list identified by its `id` field, when the diff is told to
+//!   (see [`DiffConfig::list_keys`](crate::DiffConfig::list_keys)).
+//! - `.*` and `[*]` match any single step.
+//!
+//! Field names, indices and keys may also hold glob-style wildcards, matched against the way they're
+//! printed: `*` matches any number of characters and `?` a single one, so `.created_*` matches both
+//! `created_at` and `created_by`, `[1?]` the items 10 to 19 and `["x-*"]` the keys starting with
+//! `x-`.
+//!
+//! Options are transparent: `.a.b` matches the field `b` within `A { a: Some(B { b: 1 }) }`. The
+//! empty pattern (or `.`) matches the whole value.
+
+use crate::{
+    diff::{DiffPath, DiffPathSegment},
+    parse,
+};
+use std::{error, fmt, str::FromStr};
+
+/// A pattern matching some paths within a value, as described above.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PathPattern(Vec<PatternSegment>);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PatternSegment {
+    /// A field name, which may hold wildcards.
+    Field(String),
+    /// An index or a map key, normalized to the way it's printed, which may hold wildcards.
+    Bracket(String),
+    Any,
+}
+
+impl PathPattern {
+    /// Whether `path` is exactly the one selected by this pattern.
+    pub fn matches(&self, path: &DiffPath) -> bool {
+        self.0.len() == path.len() && self.0.iter().zip(path).all(|(l, r)| l.matches(r))
+    }
+
+    /// Whether this pattern may select a path within `path`, but not `path` itself.
+    pub(crate) fn matches_within(&self, path: &DiffPath) -> bool {
+        self.0.len() > path.len() && self.0.iter().zip(path).all(|(l, r)| l.matches(r))
+    }
+}
+
+impl PatternSegment {
+    fn matches(&self, segment: &DiffPathSegment) -> bool {
+        match (self, segment) {
+            (PatternSegment::Any, _) => true,
+            (PatternSegment::Field(l), DiffPathSegment::Field(r)) => glob_matches(l, r),
+            (PatternSegment::Bracket(l), DiffPathSegment::Index(lhs, rhs)) => {
+                match l.parse::<usize>() {
+                    Ok(l) => l == *lhs || l == *rhs,
+                    Err(_) => [lhs, rhs].iter().any(|x| glob_matches(l, &x.to_string())),
+                }
+            }
+            (PatternSegment::Bracket(l), DiffPathSegment::Key(r)) => {
+                glob_matches(l, &r.to_string())
+            }
+            (PatternSegment::Bracket(l), DiffPathSegment::KeyField(k, r)) => {
+                glob_matches(l, &format!("{k}={r}"))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |message| PatternError {
+            pattern: s.to_owned(),
+            message,
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = if s == "." { "" } else { s };
+        while !rest.is_empty() {
+            if let Some(field) = rest.strip_prefix('.') {
+                let len = field.find(['.', '[']).unwrap_or(field.len());
+                let segment = match &field[..len] {
+                    "" => return Err(error("expected a field name after `.`")),
+                    "*" => PatternSegment::Any,
+                    name => PatternSegment::Field(name.to_owned()),
+                };
+
+                segments.push(segment);
+                rest = &field[len..];
+            } else if let Some(bracket) = rest.strip_prefix('[') {
+                let len = bracket_len(bracket).ok_or_else(|| error("unclosed `[`"))?;
+                let segment = match bracket[..len].trim() {
+                    "" => return Err(error("expected an index or a key within `[]`")),
+                    "*" => PatternSegment::Any,
+                    key => match parse(key) {
+                        Ok(key) => PatternSegment::Bracket(key.to_string()),
+                        Err(_) => match key.split_once('=') {
+                            Some((field, value)) => match parse(value.trim()) {
+                                Ok(value) => {
+                                    PatternSegment::Bracket(format!("{}={value}", field.trim()))
+                                }
+                                Err(_) => PatternSegment::Bracket(key.to_owned()),
+                            },
+                            None => PatternSegment::Bracket(key.to_owned()),
+                        },
+                    },
+                };
+
+                segments.push(segment);
+                rest = &bracket[len + 1..];
+            } else {
+                return Err(error("expected `.` or `[`"));
+            }
+        }
+
+        Ok(Self(segments))
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any number of characters and `?` for a
+/// single one.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // Where to resume after the last `*` when the rest doesn't match: one character further into
+    // the text than last time.
+    let mut backtrack = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&x) if x == '?' || x == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&x| x == '*')
+}
+
+/// Read a list of patterns, one per line, skipping empty lines and comments (starting with `#`).
+pub fn parse_patterns(text: &str) -> Result<Vec<PathPattern>, PatternError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(str::parse)
+        .collect()
+}
+
+/// The length of the contents of a bracket, up to the `]` which isn't within a string.
+fn bracket_len(text: &str) -> Option<usize> {
+    let mut in_str = false;
+    let mut escaped = false;
+    for (idx, x) in text.char_indices() {
+        match x {
+            _ if escaped => escaped = false,
+            '\\' if in_str => escaped = true,
+            '"' => in_str = !in_str,
+            ']' if !in_str => return Some(idx),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Why a path pattern is invalid.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternError {
+    pattern: String,
+    message: &'static str,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid path pattern `{}`: {}",
+            self.pattern, self.message
+        )
+    }
+}
+
+impl error::Error for PatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{AstItem, ValueNum, ValueStr};
+    use std::borrow::Cow;
+
+    fn field(name: &str) -> DiffPathSegment<'_, '_> {
+        DiffPathSegment::Field(name.into())
+    }
+
+    fn key(item: AstItem<'static>) -> DiffPathSegment<'static, 'static> {
+        DiffPathSegment::Key(Cow::Owned(item))
+    }
+
+    fn matches(pattern: &str, path: &DiffPath) -> bool {
+        pattern.parse::<PathPattern>().unwrap().matches(path)
+    }
+
+    #[test]
+    fn glob_fields() {
+        assert!(matches(".created_*", &vec![field("created_at")]));
+        assert!(matches(".created_*", &vec![field("created_")]));
+        assert!(!matches(".created_*", &vec![field("updated_at")]));
+        assert!(matches(".a?c", &vec![field("abc")]));
+        assert!(!matches(".a?c", &vec![field("ac")]));
+        assert!(matches(".*.id", &vec![field("user"), field("id")]));
+        assert!(!matches(".*.id", &vec![field("id")]));
+    }
+
+    #[test]
+    fn glob_indices() {
+        let at = |l, r| vec![DiffPathSegment::Index(l, r)];
+        assert!(matches("[2]", &at(2, 2)));
+        assert!(matches("[2]", &at(1, 2)));
+        assert!(!matches("[2]", &at(12, 12)));
+        assert!(matches("[1?]", &at(15, 15)));
+        assert!(!matches("[1?]", &at(1, 1)));
+        assert!(matches("[*]", &at(7, 3)));
+    }
+
+    #[test]
+    fn glob_keys() {
+        let header = |x: &'static str| vec![key(ValueStr::escape(x).into())];
+        assert!(matches(r#"["x-*"]"#, &header("x-request-id")));
+        assert!(!matches(r#"["x-*"]"#, &header("content-type")));
+        assert!(matches(r#"[ "host" ]"#, &header("host")));
+
+        let num = vec![key(ValueNum("42".into()).into())];
+        assert!(matches("[42]", &num));
+        assert!(matches("[4*]", &num));
+    }
+
+    #[test]
+    fn key_fields() {
+        let record = |x: AstItem<'static>| {
+            vec![
+                field("orders"),
+                DiffPathSegment::KeyField("id".into(), Cow::Owned(x)),
+                field("status"),
+            ]
+        };
+        let num = record(ValueNum("42".into()).into());
+        assert!(matches(".orders[id=42].status", &num));
+        assert!(matches(".orders[id=4*].status", &num));
+        assert!(matches(".orders[*].status", &num));
+        assert!(!matches(".orders[id=43].status", &num));
+        assert!(!matches(".orders[42].status", &num));
+        assert!(!matches(".orders[ref=42].status", &num));
+
+        let name = record(ValueStr::escape("a").into());
+        assert!(matches(r#".orders[id="a"].status"#, &name));
+        assert!(!matches(".orders[id=a].status", &name));
+    }
+
+    #[test]
+    fn matches_within() {
+        let pattern = ".a[*].b".parse::<PathPattern>().unwrap();
+        assert!(pattern.matches_within(&vec![]));
+        assert!(pattern.matches_within(&vec![field("a")]));
+        assert!(!pattern.matches_within(&vec![field("c")]));
+        assert!(!pattern.matches_within(&vec![
+            field("a"),
+            DiffPathSegment::Index(0, 0),
+            field("b"),
+        ]));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!("a".parse::<PathPattern>().is_err());
+        assert!(".".parse::<PathPattern>().unwrap().matches(&vec![]));
+        assert!(".a[".parse::<PathPattern>().is_err());
+        assert!(".a[]".parse::<PathPattern>().is_err());
+        assert_eq!(
+            parse_patterns("# comment\n\n.a\n  [*].b  \n")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
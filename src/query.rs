@@ -0,0 +1,407 @@
+//! Queries selecting values within a tree, in the spirit of `jq`.
+//!
+//! A query is a sequence of steps, each one turning every value selected so far into the values it
+//! selects in turn, starting from the whole tree:
+//!
+//! | Step            | Selects                                                                  |
+//! |-----------------|--------------------------------------------------------------------------|
+//! | `.name`         | the field of a struct (or the `start` or `end` of a range)               |
+//! | `[2]`, `.[2]`   | the item at that index in a list, tuple, tuple struct or set             |
+//! | `["key"]`, `[4]`| the value of a map with that key                                         |
+//! | `.*`, `[*]`     | every child                                                              |
+//! | `[?pred]`       | every child for which the predicate holds                                |
+//! | `..`            | the value itself and everything within it                                |
+//! | `Name`          | the value if it's a struct, tuple struct or unit struct called `Name`    |
+//! | `Name{}`        | the value if it's a struct with named fields called `Name`               |
+//! | `Name()`        | the value if it's a tuple struct called `Name`                           |
+//!
+//! A predicate is a query run from the child, optionally followed by a comparison against a value
+//! written the way `Debug` prints it: `[?name == "bob"]`, `[?.stats.count >= 10]`,
+//! `[?kind != Admin]`. Its first field may omit its dot, so a name alone is a field there, while
+//! `[?Foo{}]` and `[?Foo()]` are still type filters. Without a comparison, it holds when the query
+//! selects anything. Equality is structural, like a diff finding no differences, while the other
+//! comparisons (`<`, `<=`, `>`, `>=`) only hold between numbers, strings or durations.
+//!
+//! Options are transparent, like in [path patterns](crate::path): `.a.b` selects the field `b`
+//! within `A { a: Some(B { b: 1 }) }`. The empty query (or `.`) selects the whole tree.
+//!
+//! ```
+//! use debug_diff::{parse, query::Query, render::PathText};
+//!
+//! let ast = parse(r#"[User { name: "alice", id: 1 }, User { name: "bob", id: 2 }]"#).unwrap();
+//! let query = r#"[?name == "bob"].id"#.parse::<Query>().unwrap();
+//!
+//! let matches = query.select(&ast);
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(PathText(&matches[0].0).to_string(), "[1].id");
+//! assert_eq!(matches[0].1.to_string(), "2");
+//! ```
+
+use crate::{
+    ast::*,
+    diff::{DiffPath, DiffPathSegment},
+    find_diff,
+    parse::{ast_parser, run, ParseError},
+    visit::{walk_item, Visit},
+    DiffConfig,
+};
+use chumsky::{extra::Err, prelude::*};
+use std::{cmp::Ordering, str::FromStr};
+
+/// A query selecting values within a tree, as described above.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Query(Vec<Step>);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Step {
+    Field(String),
+    /// An index or a map key.
+    Bracket(AstItem<'static>),
+    Children,
+    Filter(Predicate),
+    Descendants,
+    Type(String, TypeKind),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TypeKind {
+    Any,
+    Struct,
+    TupleStruct,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Predicate {
+    query: Query,
+    comparison: Option<(CompareOp, AstItem<'static>)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A selected value, along with its path from the root of the tree.
+pub type Match<'a, 'b> = (DiffPath<'a, 'b>, &'b AstItem<'a>);
+
+impl Query {
+    /// Select the values within a tree, in the order they appear in it.
+    pub fn select<'a, 'b>(&self, item: &'b AstItem<'a>) -> Vec<Match<'a, 'b>> {
+        let mut matches = vec![(Vec::new(), item)];
+        for step in &self.0 {
+            matches = matches
+                .into_iter()
+                .flat_map(|(path, item)| step.select(path, item))
+                .collect();
+        }
+
+        matches
+    }
+}
+
+impl Step {
+    fn select<'a, 'b>(&self, path: DiffPath<'a, 'b>, item: &'b AstItem<'a>) -> Vec<Match<'a, 'b>> {
+        match self {
+            Step::Field(name) => children(path, item)
+                .into_iter()
+                .filter(
+                    |(path, _)| matches!(path.last(), Some(DiffPathSegment::Field(x)) if x == name),
+                )
+                .collect(),
+            Step::Bracket(key) => children(path, item)
+                .into_iter()
+                .filter(|(path, _)| match path.last() {
+                    Some(DiffPathSegment::Index(idx, _)) => {
+                        matches!(key, AstItem::ValueNum(x) if x.0.parse() == Ok(*idx))
+                    }
+                    Some(DiffPathSegment::Key(x)) => **x == *key,
+                    _ => false,
+                })
+                .collect(),
+            Step::Children => children(path, item),
+            Step::Filter(predicate) => children(path, item)
+                .into_iter()
+                .filter(|(_, item)| predicate.holds(item))
+                .collect(),
+            Step::Descendants => {
+                let mut collect = Collect {
+                    depth: None,
+                    matches: Vec::new(),
+                };
+                collect.visit_item(&mut path.clone(), item);
+                collect.matches
+            }
+            Step::Type(name, kind) => {
+                let item = strip_options(item);
+                let selected = match (item, kind) {
+                    (AstItem::ItemStruct(x), TypeKind::Any | TypeKind::Struct) => x.0 == *name,
+                    (AstItem::ItemTupleStruct(x), TypeKind::Any | TypeKind::TupleStruct) => {
+                        x.0 == *name
+                    }
+                    (AstItem::ItemUnitStruct(x), TypeKind::Any) => x.0 == *name,
+                    _ => false,
+                };
+                if selected {
+                    vec![(path, item)]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+    }
+}
+
+impl Predicate {
+    fn holds(&self, item: &AstItem) -> bool {
+        let selected = self.query.select(item);
+        match &self.comparison {
+            None => !selected.is_empty(),
+            Some((op, value)) => selected.into_iter().any(|(_, x)| op.holds(x, value)),
+        }
+    }
+}
+
+impl CompareOp {
+    fn holds(self, lhs: &AstItem, rhs: &AstItem) -> bool {
+        let is_equal = || match compare(lhs, rhs) {
+            Some(ordering) => ordering.is_eq(),
+            None => find_diff(lhs, rhs, &DiffConfig::default()).is_empty(),
+        };
+        match self {
+            CompareOp::Eq => is_equal(),
+            CompareOp::Ne => !is_equal(),
+            CompareOp::Lt => compare(lhs, rhs).is_some_and(Ordering::is_lt),
+            CompareOp::Le => compare(lhs, rhs).is_some_and(Ordering::is_le),
+            CompareOp::Gt => compare(lhs, rhs).is_some_and(Ordering::is_gt),
+            CompareOp::Ge => compare(lhs, rhs).is_some_and(Ordering::is_ge),
+        }
+    }
+}
+
+/// Order two numbers, strings or durations.
+fn compare(lhs: &AstItem, rhs: &AstItem) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (AstItem::ValueNum(l), AstItem::ValueNum(r)) => {
+            let (l, r) = (l.0.parse::<f64>().ok()?, r.0.parse::<f64>().ok()?);
+            l.partial_cmp(&r)
+        }
+        (AstItem::ValueStr(l), AstItem::ValueStr(r)) => Some(l.unescape().cmp(&r.unescape())),
+        (AstItem::ValueDuration(l), AstItem::ValueDuration(r)) => {
+            Some(l.to_duration()?.cmp(&r.to_duration()?))
+        }
+        _ => None,
+    }
+}
+
+/// The value within any number of `Some`.
+fn strip_options<'a, 'b>(mut item: &'b AstItem<'a>) -> &'b AstItem<'a> {
+    while let AstItem::ItemOption(ItemOption(Some(x))) = item {
+        item = x;
+    }
+
+    item
+}
+
+/// The children of a value, looking through options.
+fn children<'a, 'b>(mut path: DiffPath<'a, 'b>, item: &'b AstItem<'a>) -> Vec<Match<'a, 'b>> {
+    let mut collect = Collect {
+        depth: Some(path.len() + 1),
+        matches: Vec::new(),
+    };
+    collect.visit_item(&mut path, item);
+    collect.matches
+}
+
+/// Collects the values at some depth, or at any depth.
+struct Collect<'a, 'b> {
+    depth: Option<usize>,
+    matches: Vec<Match<'a, 'b>>,
+}
+
+impl<'a, 'b> Visit<'a, 'b> for Collect<'a, 'b> {
+    fn visit_item(&mut self, path: &mut DiffPath<'a, 'b>, item: &'b AstItem<'a>) {
+        match self.depth {
+            Some(depth) if path.len() == depth => self.matches.push((path.clone(), item)),
+            Some(_) => walk_item(self, path, item),
+            None => {
+                // `Some(x)` has the same path as `x`, which would then be selected twice.
+                if !matches!(item, AstItem::ItemOption(ItemOption(Some(_)))) {
+                    self.matches.push((path.clone(), item));
+                }
+                walk_item(self, path, item);
+            }
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        run(query_parser().padded().then_ignore(end()), s)
+    }
+}
+
+fn query_parser<'a>() -> impl Parser<'a, &'a str, Query, Err<Rich<'a, char>>> {
+    recursive(|query| {
+        let name = text::ident().map(|x: &str| x.to_owned());
+        let value = ast_parser().map(AstItem::into_owned);
+
+        let compare_op = choice((
+            just("==").to(CompareOp::Eq),
+            just("!=").to(CompareOp::Ne),
+            just("<=").to(CompareOp::Le),
+            just(">=").to(CompareOp::Ge),
+            just('<').to(CompareOp::Lt),
+            just('>').to(CompareOp::Gt),
+        ));
+        let type_kind = choice((
+            just("{}").to(TypeKind::Struct),
+            just("()").to(TypeKind::TupleStruct),
+        ));
+        // The first field may omit its dot (`[?name == "bob"]`), which leaves type filters only for
+        // the names followed by `{}` or `()`.
+        let first = name
+            .then(type_kind.or_not())
+            .map(|(name, kind)| match kind {
+                Some(kind) => Step::Type(name, kind),
+                None => Step::Field(name),
+            });
+        let predicate = first
+            .or_not()
+            .then(query)
+            .map(|(first, Query(rest))| Query(first.into_iter().chain(rest).collect()))
+            .then(compare_op.padded().then(value.clone()).or_not())
+            .map(|(query, comparison)| Predicate { query, comparison });
+        let bracket = choice((
+            just('*').to(Step::Children),
+            just('?').ignore_then(predicate.padded()).map(Step::Filter),
+            value.map(Step::Bracket),
+        ))
+        .padded()
+        .delimited_by(just('['), just(']'));
+
+        let step = choice((
+            just("..").to(Step::Descendants),
+            just('.').ignore_then(choice((
+                just('*').to(Step::Children),
+                name.map(Step::Field),
+            ))),
+            just('.').or_not().ignore_then(bracket),
+            name.then(type_kind.or_not())
+                .map(|(name, kind)| Step::Type(name, kind.unwrap_or(TypeKind::Any))),
+        ));
+
+        step.padded()
+            .repeated()
+            .collect()
+            .then_ignore(just('.').padded().or_not())
+            .map(Query)
+            .boxed()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, render::PathText};
+
+    const STATE: &str = r#"State {
+        users: [
+            User { name: "alice", id: 1, session: Some(Session { ttl: 5s }), tags: {"a": 1} },
+            User { name: "bob", id: 2, session: None, tags: {} },
+        ],
+        owner: Admin(User { name: "carol", id: 3, session: None, tags: {} }),
+        pair: (1, 2),
+    }"#;
+
+    /// The paths and values selected by a query.
+    fn select(query: &str) -> Vec<(String, String)> {
+        let ast = parse(STATE).unwrap();
+        let query = query.parse::<Query>().unwrap();
+        query
+            .select(&ast)
+            .into_iter()
+            .map(|(path, x)| (PathText(&path).to_string(), x.to_string()))
+            .collect()
+    }
+
+    fn paths(query: &str) -> Vec<String> {
+        select(query).into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(paths(""), ["."]);
+        assert_eq!(paths("."), ["."]);
+        assert_eq!(
+            select(".users[1].name"),
+            [(".users[1].name".into(), r#""bob""#.into())]
+        );
+        assert_eq!(paths(".users.[0].id"), [".users[0].id"]);
+        assert_eq!(paths(".users[*].id"), [".users[0].id", ".users[1].id"]);
+        assert_eq!(paths(".pair.*"), [".pair[0]", ".pair[1]"]);
+        assert_eq!(paths(r#".users[0].tags["a"]"#), [r#".users[0].tags["a"]"#]);
+        assert!(paths(".users[2]").is_empty());
+        assert!(paths(".missing").is_empty());
+    }
+
+    #[test]
+    fn options_are_transparent() {
+        assert_eq!(paths(".users[*].session.ttl"), [".users[0].session.ttl"]);
+        // `Some(x)` and `x` share their path, and are only selected once.
+        assert_eq!(paths("..Session"), [".users[0].session"]);
+    }
+
+    #[test]
+    fn descendants() {
+        // `..name` would be the values of type `name`.
+        assert_eq!(
+            paths("...name"),
+            [".owner[0].name", ".users[0].name", ".users[1].name"]
+        );
+        assert_eq!(paths("..User").len(), 3);
+        assert_eq!(paths("..User{}").len(), 3);
+        assert_eq!(paths("..Admin()"), [".owner"]);
+        assert!(paths("..Admin{}").is_empty());
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(paths(r#".users[?name == "bob"]"#), [".users[1]"]);
+        assert_eq!(paths(r#".users[?name != "bob"]"#), [".users[0]"]);
+        assert_eq!(paths(".users[?id >= 2]"), [".users[1]"]);
+        assert_eq!(paths(".users[?id < 2].name"), [".users[0].name"]);
+        assert_eq!(paths(".users[?.session.ttl > 1s]"), [".users[0]"]);
+        assert_eq!(paths(".users[?session]"), [".users[0]", ".users[1]"]);
+        assert_eq!(paths(".users[?session == None]"), [".users[1]"]);
+        assert_eq!(paths(".users[?tags == {}]"), [".users[1]"]);
+        // Ordering doesn't hold between values of different kinds.
+        assert!(paths(r#".users[?id > "a"]"#).is_empty());
+    }
+
+    #[test]
+    fn type_filters_in_predicates() {
+        assert_eq!(
+            paths(".*[?User{}]"),
+            [".owner[0]", ".users[0]", ".users[1]"]
+        );
+        // An empty predicate selects the child itself.
+        assert_eq!(paths(".pair[?]"), [".pair[0]", ".pair[1]"]);
+        assert_eq!(paths(".users[?Session{}]").len(), 0);
+        assert_eq!(paths(".owner[?User{}.id == 3]"), [".owner[0]"]);
+        assert_eq!(paths(".[?Admin()]"), [".owner"]);
+    }
+
+    #[test]
+    fn invalid_queries() {
+        for query in ["[", "users[", ".a ==", "[?a ==]", ".[1"] {
+            assert!(query.parse::<Query>().is_err(), "{query}");
+        }
+    }
+}
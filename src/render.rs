@@ -0,0 +1,245 @@
+//! Human-readable reports of differences.
+
+use crate::{
+    ast::AstItem,
+    diff::{DiffItem, DiffPath, DiffPathSegment, DiffPayload},
+    util::HexWindow,
+};
+use std::fmt;
+
+/// A list of differences, one (or a few) lines each.
+pub struct Report<'r, 'a, 'b>(pub &'r [DiffItem<'a, 'b>]);
+
+impl<'r, 'a, 'b> fmt::Display for Report<'r, 'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Found {} differences.", self.0.len())?;
+        writeln!(f)?;
+
+        for diff in self.0 {
+            match &diff.diff {
+                DiffPayload::AstItem(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Type mismatch: expected {} `{r}`, but got {} `{l}` at {}",
+                        kind_name(r),
+                        kind_name(l),
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ItemRange(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Range type mismatch: expected {r}, but got {l} at {}",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ItemStruct(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Struct type mismatch: expected {}, but got {} at {}",
+                        r.0,
+                        l.0,
+                        PathText(&diff.path)
+                    )?;
+                    if l.0 == r.0 {
+                        writeln!(f, "      Note: The internal structure differs.")?;
+                    }
+                }
+                DiffPayload::ItemTuple(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Tuple type mismatch: expected {} elements, but got {} at {}",
+                        r.0.len(),
+                        l.0.len(),
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ItemTupleStruct(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Tuple struct type mismatch: expected {}, but got {} at {}",
+                        r.0,
+                        l.0,
+                        PathText(&diff.path)
+                    )?;
+                    if l.0 == r.0 {
+                        writeln!(f, "      Note: The internal structure differs.")?;
+                    }
+                }
+                DiffPayload::ItemUnitStruct(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Enum or type mismatch: expected {}, but got {} at {}",
+                        r.0,
+                        l.0,
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueBool(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Boolean mismatch: expected {}, but got {} at {}.",
+                        r.0,
+                        l.0,
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueBytes(l, r) => {
+                    let (l, r) = (l.to_bytes(), r.to_bytes());
+                    let offset = l.iter().zip(&r).take_while(|(a, b)| a == b).count();
+                    writeln!(
+                        f,
+                        "  - Byte string mismatch: expected {} bytes, but got {} at {}.",
+                        r.len(),
+                        l.len(),
+                        PathText(&diff.path)
+                    )?;
+                    writeln!(f, "      First difference at offset {offset}:")?;
+                    writeln!(f, "      Expected: {}", HexWindow(&r, offset))?;
+                    writeln!(f, "      Got     : {}", HexWindow(&l, offset))?;
+                }
+                DiffPayload::ValueDuration(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Duration mismatch: expected {r}, but got {l} at {}.",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueIpAddr(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Address mismatch: expected {r}, but got {l} at {}.",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueNum(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Number mismatch: expected {}, but got {} at {}.",
+                        r.0,
+                        l.0,
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueOpaque(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Opaque value mismatch: expected {r}, but got {l} at {}.",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueSocketAddr(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Socket address mismatch: expected {r}, but got {l} at {}.",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::ValueStr(l, r) => {
+                    writeln!(
+                        f,
+                        "  - String mismatch: expected {r}, but got {l} at {}.",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::InsertedAt(i, r) => {
+                    writeln!(
+                        f,
+                        "  - Source set (or option) is missing an item at index {i}: {r:?} at {}",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::RemovedAt(i, l) => {
+                    writeln!(
+                        f,
+                        "  - Source set (or option) has an extra item at index {i}: {l:?} at {}",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::InsertedPair(k, r) => {
+                    writeln!(
+                        f,
+                        "  - Source mapping is missing an entry at {}:",
+                        PathText(&diff.path)
+                    )?;
+                    writeln!(f, "      Key  : {k}")?;
+                    writeln!(f, "      Value: {r}")?;
+                }
+                DiffPayload::RemovedPair(k, l) => {
+                    writeln!(
+                        f,
+                        "  - Source mapping has an extra entry at {}",
+                        PathText(&diff.path)
+                    )?;
+                    writeln!(f, "      Key  : {k}")?;
+                    writeln!(f, "      Value: {l}")?;
+                }
+                DiffPayload::DuplicateKey(l, r) => {
+                    writeln!(
+                        f,
+                        "  - Duplicate key: {l} items hold it, {r} expected, at {}; they're compared in order.",
+                        PathText(&diff.path)
+                    )?;
+                }
+                DiffPayload::CountChanged(x, l, r) => {
+                    writeln!(
+                        f,
+                        "  - Count mismatch: expected {r}, but got {l} of {x:?} at {}.",
+                        PathText(&diff.path)
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What kind of value an item is, for values of different kinds.
+fn kind_name(item: &AstItem) -> &'static str {
+    match item {
+        AstItem::ItemEmpty(_) => "an empty map or set",
+        AstItem::ItemList(_) => "a list",
+        AstItem::ItemMap(_) => "a map",
+        AstItem::ItemOption(_) => "an option",
+        AstItem::ItemRange(_) => "a range",
+        AstItem::ItemSet(_) => "a set",
+        AstItem::ItemStruct(_) => "a struct",
+        AstItem::ItemTuple(_) => "a tuple",
+        AstItem::ItemTupleStruct(_) => "a tuple struct",
+        AstItem::ItemUnitStruct(_) => "a unit struct",
+        AstItem::ValueBool(_) => "a boolean",
+        AstItem::ValueBytes(_) => "a byte string",
+        AstItem::ValueDuration(_) => "a duration",
+        AstItem::ValueIpAddr(_) => "an address",
+        AstItem::ValueNum(_) => "a number",
+        AstItem::ValueOpaque(_) => "an opaque value",
+        AstItem::ValueSocketAddr(_) => "a socket address",
+        AstItem::ValueStr(_) => "a string",
+    }
+}
+
+/// A path written the way [path patterns](crate::path) are (`.items[2].id`), or `.` for the root.
+///
+/// An index which differs on both sides is written as `[1->2]`.
+pub struct PathText<'r, 'a, 'b>(pub &'r DiffPath<'a, 'b>);
+
+impl<'r, 'a, 'b> fmt::Display for PathText<'r, 'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str(".");
+        }
+
+        for segment in self.0 {
+            match segment {
+                DiffPathSegment::Field(x) => write!(f, ".{x}")?,
+                DiffPathSegment::Index(l, r) if l == r => write!(f, "[{l}]")?,
+                DiffPathSegment::Index(l, r) => write!(f, "[{l}->{r}]")?,
+                DiffPathSegment::Key(x) => write!(f, "[{x}]")?,
+                DiffPathSegment::KeyField(k, x) => write!(f, "[{k}={x}]")?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,544 @@
+//! Conversion of any value implementing `Serialize` into a tree, without going through its text.
+//!
+//! The tree is the one the parser would build from the `Debug` output of the value, as long as
+//! `Debug` and `Serialize` are both derived, except for sets: `Serialize` writes them the same way
+//! as sequences, so they become lists.
+//!
+//! | Value                           | Converted into                                           |
+//! |---------------------------------|----------------------------------------------------------|
+//! | `Foo { a: 1 }`, `E::V { a: 1 }` | `ItemStruct("Foo", ..)`, `ItemStruct("V", ..)`           |
+//! | `Foo(1)`, `E::V(1, 2)`          | `ItemTupleStruct("Foo", ..)`, `ItemTupleStruct("V", ..)` |
+//! | `Foo`, `Foo {}`, `E::V`         | `ItemUnitStruct("Foo")`, `ItemUnitStruct("V")`           |
+//! | `Some(1)`, `None`               | `ItemOption`                                             |
+//! | sequences, sets                 | `ItemList`                                               |
+//! | tuples, `()`                    | `ItemTuple`                                              |
+//! | maps                            | `ItemMap` (`ItemEmpty` when empty)                       |
+//! | strings, characters             | `ValueStr`                                               |
+//! | bytes (with `serde_bytes`)      | `ValueBytes`                                             |
+//! | `Duration`                      | `ValueDuration`                                          |
+//! | `NaN`, `inf`                    | `ItemUnitStruct`                                         |
+//! | other numbers                   | `ValueNum`                                               |
+//!
+//! The tree doesn't borrow the value, since its text (numbers, escaped strings...) has to be built.
+
+use crate::ast::*;
+use serde::{ser, Serialize};
+use std::{borrow::Cow, collections::BTreeMap, error, fmt, time::Duration};
+
+/// Convert a value into a tree, as described above.
+pub fn to_ast<T>(value: &T) -> Result<AstItem<'static>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(Serializer)
+}
+
+/// Why a value couldn't be converted, as reported by its `Serialize` implementation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// A `Serializer` building a tree.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Serializer;
+
+impl Serializer {
+    fn num(self, value: impl fmt::Debug) -> Result<AstItem<'static>, Error> {
+        Ok(ValueNum(format!("{value:?}").into()).into())
+    }
+
+    fn float<F: fmt::Debug + Into<f64> + Copy>(self, value: F) -> Result<AstItem<'static>, Error> {
+        // `Debug` prints these as names, which read back as unit structs (but `-inf` as a number).
+        match value.into() {
+            x if x.is_nan() => Ok(ItemUnitStruct("NaN".into()).into()),
+            f64::INFINITY => Ok(ItemUnitStruct("inf".into()).into()),
+            _ => self.num(value),
+        }
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeSeq;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStruct;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(ValueBool(v).into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.num(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.float(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.float(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(ValueStr::escape(v).into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(ValueBytes(v.escape_ascii().to_string().into()).into())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(ItemOption(None).into())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(ItemOption(Some(Box::new(value.serialize(self)?))).into())
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(ItemTuple(Vec::new()).into())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(ItemUnitStruct(name.into()).into())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(ItemUnitStruct(variant.into()).into())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(ItemTupleStruct(name.into(), vec![value.serialize(self)?]).into())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(ItemTupleStruct(variant.into(), vec![value.serialize(self)?]).into())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SerializeSeq::new(SeqKind::List, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(SerializeSeq::new(SeqKind::Tuple, Some(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(SerializeSeq::new(SeqKind::TupleStruct(name), Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SerializeSeq::new(SeqKind::TupleStruct(variant), Some(len)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(SerializeMap {
+            entries: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(SerializeStruct {
+            name,
+            fields: BTreeMap::new(),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.serialize_struct(variant, len)
+    }
+}
+
+enum SeqKind {
+    List,
+    Tuple,
+    TupleStruct(&'static str),
+}
+
+pub struct SerializeSeq {
+    kind: SeqKind,
+    items: Vec<AstItem<'static>>,
+}
+
+impl SerializeSeq {
+    fn new(kind: SeqKind, len: Option<usize>) -> Self {
+        Self {
+            kind,
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        }
+    }
+
+    fn push<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<AstItem<'static>, Error> {
+        Ok(match self.kind {
+            SeqKind::List => ItemList(self.items).into(),
+            SeqKind::Tuple => ItemTuple(self.items).into(),
+            SeqKind::TupleStruct(name) => ItemTupleStruct(name.into(), self.items).into(),
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeSeq {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+pub struct SerializeMap {
+    entries: BTreeMap<AstItem<'static>, AstItem<'static>>,
+    /// The key whose value comes next.
+    key: Option<AstItem<'static>>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("map value without a key".to_owned()))?;
+        self.entries.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        // `Debug` prints empty maps as `{}`, which could also be an empty set.
+        if self.entries.is_empty() {
+            return Ok(ItemEmpty.into());
+        }
+
+        Ok(ItemMap(self.entries).into())
+    }
+}
+
+pub struct SerializeStruct {
+    name: &'static str,
+    fields: BTreeMap<Cow<'static, str>, AstItem<'static>>,
+}
+
+impl SerializeStruct {
+    /// The `Duration` this struct was written from, if any.
+    fn to_duration(&self) -> Option<Duration> {
+        let field = |name| match self.fields.get(name)? {
+            AstItem::ValueNum(x) => Some(x),
+            _ => None,
+        };
+        if self.name != "Duration" || self.fields.len() != 2 {
+            return None;
+        }
+
+        Some(Duration::new(
+            field("secs")?.0.parse().ok()?,
+            field("nanos")?.0.parse().ok()?,
+        ))
+    }
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.fields.insert(key.into(), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(duration) = self.to_duration() {
+            return Ok(ValueDuration(format!("{duration:?}").into()).into());
+        }
+        // `Debug` prints a struct without fields as its name alone.
+        if self.fields.is_empty() {
+            return Ok(ItemUnitStruct(self.name.into()).into());
+        }
+
+        Ok(ItemStruct(self.name.into(), self.fields, false).into())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeStruct {
+    type Ok = AstItem<'static>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeStruct::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use serde::Serialize;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Check that `value` converts into the tree its `Debug` output parses into.
+    fn check<T: fmt::Debug + Serialize>(value: T) {
+        let text = format!("{value:?}");
+        assert_eq!(to_ast(&value).unwrap(), parse(&text).unwrap(), "{text}");
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Foo {
+        a: u8,
+        b: Option<&'static str>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Id(u8);
+
+    #[derive(Debug, Serialize)]
+    struct Pair(u8, u8);
+
+    #[derive(Debug, Serialize)]
+    struct Marker;
+
+    #[derive(Debug, Serialize)]
+    struct Empty {}
+
+    #[derive(Debug, Serialize)]
+    enum E {
+        S { a: u8 },
+        T(u8, u8),
+        N(u8),
+        U,
+        Empty {},
+    }
+
+    #[test]
+    fn table() {
+        check(Foo { a: 1, b: Some("x") });
+        check(E::S { a: 1 });
+        check(Id(1));
+        check(Pair(1, 2));
+        check(E::T(1, 2));
+        check(E::N(1));
+        check(Marker);
+        check(Empty {});
+        check(E::U);
+        check(E::Empty {});
+        check(Some(1));
+        check(None::<u8>);
+        check(vec![1, 2]);
+        check(Vec::<u8>::new());
+        check((1, "a", ()));
+        check(());
+        check(BTreeMap::from([("a", 1), ("b", 2)]));
+        check(BTreeMap::from([((1, 2), vec![3])]));
+        check(BTreeMap::<u8, u8>::new());
+        check("a \"b\"\n\u{7f}");
+        check(Duration::from_millis(1500));
+        check(Duration::from_nanos(7));
+        check(Duration::from_secs(90061));
+        check((
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            1e20,
+            -1.5e-9,
+            0.1,
+        ));
+        check((f32::NAN, f32::INFINITY, 0.1f32, 3e38f32));
+        check((u128::MAX, i128::MIN, -1i8));
+    }
+
+    #[test]
+    fn sets_and_characters() {
+        // Sets are written like sequences.
+        let set = BTreeSet::from([1, 2]);
+        assert_eq!(to_ast(&set).unwrap(), parse("[1, 2]").unwrap());
+        assert_eq!(to_ast(&'x').unwrap(), parse("\"x\"").unwrap());
+    }
+}
@@ -0,0 +1,285 @@
+//! Snapshot tests comparing values against their `{:#?}` output stored in a file.
+//!
+//! The first run of [`debug_snapshot!`](crate::debug_snapshot) writes the snapshot into
+//! `snapshots/<module>__<test>.snap`, within the directory of the crate being tested. Later runs
+//! compare the value against it structurally, so that things like the order of a `HashMap` don't
+//! matter. When they differ, the new snapshot is written next to the old one with a `.snap.new`
+//! extension, to be accepted or rejected with `debug-diff review`.
+
+use crate::{
+    ast::{AstItem, OpaqueKind, Pretty, PrettyConfig, ValueOpaque},
+    diff::DiffPath,
+    find_diff, parse,
+    path::PathPattern,
+    render::Report,
+    visit::{walk_item_mut, VisitMut},
+    DiffConfig,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "<redacted>";
+
+/// Asserts that a value matches its stored snapshot, as described in the [module
+/// documentation](crate::snapshot).
+///
+/// The values at the given [paths](crate::path) are replaced with `<redacted>`, which is useful
+/// for timestamps, random identifiers and the like.
+///
+/// ```no_run
+/// use debug_diff::debug_snapshot;
+///
+/// #[derive(Debug)]
+/// struct User {
+///     id: u64,
+///     name: &'static str,
+/// }
+///
+/// debug_snapshot!(User { id: 1, name: "ferris" });
+/// debug_snapshot!(User { id: 2, name: "ferris" }, redact: [".id"]);
+/// ```
+#[macro_export]
+macro_rules! debug_snapshot {
+    ($value:expr $(,)?) => {
+        $crate::debug_snapshot!($value, redact: [])
+    };
+    ($value:expr, redact: [$($path:expr),* $(,)?] $(,)?) => {{
+        fn f() {}
+        $crate::snapshot::assert_snapshot(
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")),
+            ::std::any::type_name_of_val(&f),
+            &$value,
+            &[$($path),*],
+        )
+    }};
+}
+
+/// How many snapshots each test took so far, so that every one gets its own file.
+static COUNTERS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_snapshot(manifest_dir: &Path, function: &str, value: &dyn Debug, redact: &[&str]) {
+    let name = snapshot_name(function);
+    let path = manifest_dir.join("snapshots").join(format!("{name}.snap"));
+    let new_path = path.with_extension("snap.new");
+
+    let mut fresh = format!("{value:#?}");
+    if !redact.is_empty() {
+        let patterns = redact
+            .iter()
+            .map(|x| x.parse::<PathPattern>())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|error| panic!("{error}"));
+        let mut ast = parse(&fresh)
+            .unwrap_or_else(|error| panic!("snapshot `{name}` can't be redacted: {error}"));
+
+        Redact(&patterns).visit_item_mut(&mut Vec::new(), &mut ast);
+        fresh = Pretty(&ast, &PrettyConfig::default()).to_string();
+    }
+    fresh.push('\n');
+
+    let write = |path: &Path, text: String| {
+        fs::create_dir_all(path.parent().unwrap_or(path))
+            .and_then(|()| fs::write(path, text))
+            .unwrap_or_else(|error| panic!("{} can't be written: {error}", path.display()));
+    };
+    let stored = match fs::read_to_string(&path) {
+        Ok(stored) => stored,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            write(&path, fresh);
+            return;
+        }
+        Err(error) => panic!("snapshot `{name}` can't be read: {error}"),
+    };
+
+    let message = match (parse(&fresh), parse(&stored)) {
+        _ if fresh == stored => None,
+        (Ok(ast_fresh), Ok(ast_stored)) => {
+            let diffs = find_diff(&ast_fresh, &ast_stored, &DiffConfig::default());
+            (!diffs.is_empty()).then(|| Report(&diffs).to_string())
+        }
+        _ => Some(format!(
+            "  new: {}\n  old: {}",
+            fresh.trim_end(),
+            stored.trim_end()
+        )),
+    };
+
+    match message {
+        Some(message) => {
+            write(&new_path, fresh);
+            panic!(
+                "snapshot `{name}` doesn't match\n{message}\nRun `debug-diff review` to accept or \
+                 reject {}.",
+                new_path.display()
+            );
+        }
+        None => match reject(&new_path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                panic!("{} can't be removed: {error}", new_path.display())
+            }
+            _ => {}
+        },
+    }
+}
+
+/// The name of the snapshot file for a test, from the path of a function defined within it
+/// (`my_crate::tests::parses::f`).
+fn snapshot_name(function: &str) -> String {
+    let test = function.strip_suffix("::f").unwrap_or(function);
+    let test = test.split_once("::").map_or(test, |(_, x)| x);
+    let name = test.replace("::", "__");
+
+    let mut counters = COUNTERS.lock().unwrap_or_else(|x| x.into_inner());
+    let counter = counters.entry(name.clone()).or_default();
+    *counter += 1;
+
+    match *counter {
+        1 => name,
+        n => format!("{name}-{n}"),
+    }
+}
+
+/// Replaces every value whose path matches one of the patterns with [`REDACTED`].
+struct Redact<'p>(&'p [PathPattern]);
+
+impl<'a> VisitMut<'a> for Redact<'_> {
+    fn visit_item_mut(&mut self, path: &mut DiffPath<'a, 'a>, item: &mut AstItem<'a>) {
+        if self.0.iter().any(|x| x.matches(path)) {
+            *item = ValueOpaque(OpaqueKind::Placeholder, REDACTED.into()).into();
+        } else {
+            walk_item_mut(self, path, item);
+        }
+    }
+}
+
+/// Find the pending snapshots (`.snap.new` files) within a directory and its subdirectories.
+pub fn pending(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            // Build outputs may be huge, and never hold snapshots.
+            if !path.ends_with("target") && !path.ends_with(".git") {
+                paths.extend(pending(&path)?);
+            }
+        } else if path.to_string_lossy().ends_with(".snap.new") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// Replace a snapshot with its pending version (`.snap.new` file).
+pub fn accept(new_path: &Path) -> io::Result<()> {
+    fs::rename(new_path, new_path.with_extension(""))
+}
+
+/// Drop the pending version of a snapshot, keeping the snapshot as it is.
+pub fn reject(new_path: &Path) -> io::Result<()> {
+    fs::remove_file(new_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        panic::{self, AssertUnwindSafe},
+        process, slice,
+    };
+
+    /// An empty directory only used by one test.
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("debug-diff-{}-{test}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Take a snapshot the way a new run of a test would, returning the message it panics with.
+    fn snapshot(dir: &Path, test: &str, value: &dyn Debug, redact: &[&str]) -> Result<(), String> {
+        COUNTERS.lock().unwrap().remove(&format!("tests__{test}"));
+        let function = format!("my_crate::tests::{test}::f");
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            assert_snapshot(dir, &function, value, redact)
+        }))
+        .map_err(|error| error.downcast_ref::<String>().cloned().unwrap_or_default())
+    }
+
+    #[test]
+    fn pending_and_accept() {
+        let dir = scratch_dir("pending");
+        let path = dir.join("snapshots/tests__flow.snap");
+        let new_path = dir.join("snapshots/tests__flow.snap.new");
+
+        assert_eq!(snapshot(&dir, "flow", &vec![1, 2], &[]), Ok(()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[\n    1,\n    2,\n]\n");
+        assert_eq!(snapshot(&dir, "flow", &vec![1, 2], &[]), Ok(()));
+
+        let message = snapshot(&dir, "flow", &vec![1, 3], &[]).unwrap_err();
+        assert!(
+            message.contains("snapshot `tests__flow` doesn't match"),
+            "{message}"
+        );
+        assert_eq!(pending(&dir).unwrap(), slice::from_ref(&new_path));
+
+        // Matching again drops the pending snapshot.
+        assert_eq!(snapshot(&dir, "flow", &vec![1, 2], &[]), Ok(()));
+        assert!(pending(&dir).unwrap().is_empty());
+
+        assert!(snapshot(&dir, "flow", &vec![1, 3], &[]).is_err());
+        reject(&new_path).unwrap();
+        assert!(pending(&dir).unwrap().is_empty());
+        assert!(snapshot(&dir, "flow", &vec![1, 3], &[]).is_err());
+        accept(&new_path).unwrap();
+        assert!(pending(&dir).unwrap().is_empty());
+        assert_eq!(snapshot(&dir, "flow", &vec![1, 3], &[]), Ok(()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn redaction() {
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Session {
+            id: u64,
+            user: &'static str,
+            tokens: Vec<&'static str>,
+        }
+
+        let dir = scratch_dir("redaction");
+        let redact = [".id", ".tokens[*]"];
+        let session = |id, user| Session {
+            id,
+            user,
+            tokens: vec!["a", "b"],
+        };
+
+        assert_eq!(
+            snapshot(&dir, "redacted", &session(1, "ann"), &redact),
+            Ok(())
+        );
+        let stored = fs::read_to_string(dir.join("snapshots/tests__redacted.snap")).unwrap();
+        assert!(stored.contains("id: <redacted>"), "{stored}");
+        assert!(stored.contains("user: \"ann\""), "{stored}");
+        assert!(!stored.contains("\"a\""), "{stored}");
+
+        assert_eq!(
+            snapshot(&dir, "redacted", &session(2, "ann"), &redact),
+            Ok(())
+        );
+        assert!(snapshot(&dir, "redacted", &session(2, "bob"), &redact).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -13,3 +13,34 @@ where
         self.0.fmt(f)
     }
 }
+
+/// Formats the bytes around an offset in hexadecimal, highlighting the byte at the offset.
+pub struct HexWindow<'a>(pub &'a [u8], pub usize);
+
+impl<'a> fmt::Display for HexWindow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = self.1.saturating_sub(8).min(self.0.len());
+        let end = (start + 16).min(self.0.len());
+
+        if start > 0 {
+            f.write_str(".. ")?;
+        }
+        for (idx, byte) in self.0[start..end].iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" ")?;
+            }
+            if start + idx == self.1 {
+                write!(f, "[{byte:02x}]")?;
+            } else {
+                write!(f, "{byte:02x}")?;
+            }
+        }
+        if end < self.0.len() {
+            f.write_str(" ..")?;
+        } else if self.1 >= self.0.len() {
+            f.write_str(if self.0.is_empty() { "[end]" } else { " [end]" })?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,827 @@
+//! Traversals of trees, keeping track of the path to the current value.
+//!
+//! [`Visit`] walks a borrowed tree, [`VisitMut`] changes one in place and [`Fold`] rebuilds one
+//! it owns. Each trait has a method per type of value, which by default walks into its children
+//! (using the `walk_*` function of the same name), so that a pass only needs to implement the
+//! methods for the values it's interested in. When it does, calling the `walk_*` function keeps
+//! walking into the children.
+//!
+//! The path is the one a [`DiffItem`](crate::DiffItem) would have:
+//!
+//! - The fields of a struct are `Field` segments, and so are the `start` and `end` of a range.
+//! - The items of a list, tuple or set are `Index` segments (with the same index on both sides).
+//!   Sets are walked in order. [`VisitMut`] and [`Fold`] then build them again from the changed
+//!   items, so items which became equal are merged.
+//! - The values of a map are `Key` segments. The keys themselves aren't walked.
+//! - Options don't add a segment.
+//!
+//! ```
+//! use debug_diff::{ast::ValueNum, parse, visit::Visit, DiffPath};
+//!
+//! #[derive(Default)]
+//! struct Numbers(Vec<String>);
+//!
+//! impl<'a, 'b> Visit<'a, 'b> for Numbers {
+//!     fn visit_value_num(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ValueNum<'a>) {
+//!         self.0.push(format!("{path:?} {x}"));
+//!     }
+//! }
+//!
+//! let ast = parse("Point { x: 1, y: Some(2) }").unwrap();
+//! let mut numbers = Numbers::default();
+//! numbers.visit_item(&mut Vec::new(), &ast);
+//! assert_eq!(numbers.0, [r#"[Field("x")] 1"#, r#"[Field("y")] 2"#]);
+//! ```
+
+use crate::{
+    ast::*,
+    diff::{DiffPath, DiffPathSegment},
+};
+use std::{borrow::Cow, mem};
+
+/// A traversal of a borrowed tree.
+///
+/// The segments of the path borrow the tree.
+pub trait Visit<'a, 'b> {
+    fn visit_item(&mut self, path: &mut DiffPath<'a, 'b>, item: &'b AstItem<'a>) {
+        walk_item(self, path, item)
+    }
+
+    fn visit_item_empty(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ItemEmpty) {}
+
+    fn visit_item_list(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemList<'a>) {
+        walk_item_list(self, path, x)
+    }
+
+    fn visit_item_map(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemMap<'a>) {
+        walk_item_map(self, path, x)
+    }
+
+    fn visit_item_option(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemOption<'a>) {
+        walk_item_option(self, path, x)
+    }
+
+    fn visit_item_range(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemRange<'a>) {
+        walk_item_range(self, path, x)
+    }
+
+    fn visit_item_set(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemSet<'a>) {
+        walk_item_set(self, path, x)
+    }
+
+    fn visit_item_struct(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemStruct<'a>) {
+        walk_item_struct(self, path, x)
+    }
+
+    fn visit_item_tuple(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemTuple<'a>) {
+        walk_item_tuple(self, path, x)
+    }
+
+    fn visit_item_tuple_struct(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ItemTupleStruct<'a>) {
+        walk_item_tuple_struct(self, path, x)
+    }
+
+    fn visit_item_unit_struct(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ItemUnitStruct<'a>) {
+    }
+
+    fn visit_value_bool(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueBool) {}
+
+    fn visit_value_bytes(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueBytes<'a>) {}
+
+    fn visit_value_duration(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueDuration<'a>) {}
+
+    fn visit_value_ip_addr(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueIpAddr<'a>) {}
+
+    fn visit_value_num(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueNum<'a>) {}
+
+    fn visit_value_opaque(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueOpaque<'a>) {}
+
+    fn visit_value_socket_addr(
+        &mut self,
+        _path: &mut DiffPath<'a, 'b>,
+        _x: &'b ValueSocketAddr<'a>,
+    ) {
+    }
+
+    fn visit_value_str(&mut self, _path: &mut DiffPath<'a, 'b>, _x: &'b ValueStr<'a>) {}
+}
+
+/// A traversal changing a tree in place.
+///
+/// The segments of the path are copied from the tree, since it can't be borrowed while it's being
+/// changed.
+pub trait VisitMut<'a> {
+    fn visit_item_mut(&mut self, path: &mut DiffPath<'a, 'a>, item: &mut AstItem<'a>) {
+        walk_item_mut(self, path, item)
+    }
+
+    fn visit_item_empty_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ItemEmpty) {}
+
+    fn visit_item_list_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemList<'a>) {
+        walk_item_list_mut(self, path, x)
+    }
+
+    fn visit_item_map_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemMap<'a>) {
+        walk_item_map_mut(self, path, x)
+    }
+
+    fn visit_item_option_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemOption<'a>) {
+        walk_item_option_mut(self, path, x)
+    }
+
+    fn visit_item_range_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemRange<'a>) {
+        walk_item_range_mut(self, path, x)
+    }
+
+    fn visit_item_set_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemSet<'a>) {
+        walk_item_set_mut(self, path, x)
+    }
+
+    fn visit_item_struct_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemStruct<'a>) {
+        walk_item_struct_mut(self, path, x)
+    }
+
+    fn visit_item_tuple_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ItemTuple<'a>) {
+        walk_item_tuple_mut(self, path, x)
+    }
+
+    fn visit_item_tuple_struct_mut(
+        &mut self,
+        path: &mut DiffPath<'a, 'a>,
+        x: &mut ItemTupleStruct<'a>,
+    ) {
+        walk_item_tuple_struct_mut(self, path, x)
+    }
+
+    fn visit_item_unit_struct_mut(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        _x: &mut ItemUnitStruct<'a>,
+    ) {
+    }
+
+    fn visit_value_bool_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ValueBool) {}
+
+    fn visit_value_bytes_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ValueBytes<'a>) {}
+
+    fn visit_value_duration_mut(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        _x: &mut ValueDuration<'a>,
+    ) {
+    }
+
+    fn visit_value_ip_addr_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ValueIpAddr<'a>) {}
+
+    fn visit_value_num_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ValueNum<'a>) {}
+
+    fn visit_value_opaque_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ValueOpaque<'a>) {}
+
+    fn visit_value_socket_addr_mut(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        _x: &mut ValueSocketAddr<'a>,
+    ) {
+    }
+
+    fn visit_value_str_mut(&mut self, _path: &mut DiffPath<'a, 'a>, _x: &mut ValueStr<'a>) {}
+}
+
+/// A traversal rebuilding a tree, which may replace any value with another one.
+///
+/// The segments of the path are copied from the tree, like for [`VisitMut`].
+pub trait Fold<'a> {
+    fn fold_item(&mut self, path: &mut DiffPath<'a, 'a>, item: AstItem<'a>) -> AstItem<'a> {
+        walk_item_fold(self, path, item)
+    }
+
+    fn fold_item_empty(&mut self, _path: &mut DiffPath<'a, 'a>, x: ItemEmpty) -> ItemEmpty {
+        x
+    }
+
+    fn fold_item_list(&mut self, path: &mut DiffPath<'a, 'a>, x: ItemList<'a>) -> ItemList<'a> {
+        walk_item_list_fold(self, path, x)
+    }
+
+    fn fold_item_map(&mut self, path: &mut DiffPath<'a, 'a>, x: ItemMap<'a>) -> ItemMap<'a> {
+        walk_item_map_fold(self, path, x)
+    }
+
+    fn fold_item_option(
+        &mut self,
+        path: &mut DiffPath<'a, 'a>,
+        x: ItemOption<'a>,
+    ) -> ItemOption<'a> {
+        walk_item_option_fold(self, path, x)
+    }
+
+    fn fold_item_range(&mut self, path: &mut DiffPath<'a, 'a>, x: ItemRange<'a>) -> ItemRange<'a> {
+        walk_item_range_fold(self, path, x)
+    }
+
+    fn fold_item_set(&mut self, path: &mut DiffPath<'a, 'a>, x: ItemSet<'a>) -> ItemSet<'a> {
+        walk_item_set_fold(self, path, x)
+    }
+
+    fn fold_item_struct(
+        &mut self,
+        path: &mut DiffPath<'a, 'a>,
+        x: ItemStruct<'a>,
+    ) -> ItemStruct<'a> {
+        walk_item_struct_fold(self, path, x)
+    }
+
+    fn fold_item_tuple(&mut self, path: &mut DiffPath<'a, 'a>, x: ItemTuple<'a>) -> ItemTuple<'a> {
+        walk_item_tuple_fold(self, path, x)
+    }
+
+    fn fold_item_tuple_struct(
+        &mut self,
+        path: &mut DiffPath<'a, 'a>,
+        x: ItemTupleStruct<'a>,
+    ) -> ItemTupleStruct<'a> {
+        walk_item_tuple_struct_fold(self, path, x)
+    }
+
+    fn fold_item_unit_struct(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        x: ItemUnitStruct<'a>,
+    ) -> ItemUnitStruct<'a> {
+        x
+    }
+
+    fn fold_value_bool(&mut self, _path: &mut DiffPath<'a, 'a>, x: ValueBool) -> ValueBool {
+        x
+    }
+
+    fn fold_value_bytes(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        x: ValueBytes<'a>,
+    ) -> ValueBytes<'a> {
+        x
+    }
+
+    fn fold_value_duration(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        x: ValueDuration<'a>,
+    ) -> ValueDuration<'a> {
+        x
+    }
+
+    fn fold_value_ip_addr(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        x: ValueIpAddr<'a>,
+    ) -> ValueIpAddr<'a> {
+        x
+    }
+
+    fn fold_value_num(&mut self, _path: &mut DiffPath<'a, 'a>, x: ValueNum<'a>) -> ValueNum<'a> {
+        x
+    }
+
+    fn fold_value_opaque(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        x: ValueOpaque<'a>,
+    ) -> ValueOpaque<'a> {
+        x
+    }
+
+    fn fold_value_socket_addr(
+        &mut self,
+        _path: &mut DiffPath<'a, 'a>,
+        x: ValueSocketAddr<'a>,
+    ) -> ValueSocketAddr<'a> {
+        x
+    }
+
+    fn fold_value_str(&mut self, _path: &mut DiffPath<'a, 'a>, x: ValueStr<'a>) -> ValueStr<'a> {
+        x
+    }
+}
+
+/// Call the method of the visitor for the type of the value.
+pub fn walk_item<'a, 'b, V>(visitor: &mut V, path: &mut DiffPath<'a, 'b>, item: &'b AstItem<'a>)
+where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    match item {
+        AstItem::ItemEmpty(x) => visitor.visit_item_empty(path, x),
+        AstItem::ItemList(x) => visitor.visit_item_list(path, x),
+        AstItem::ItemMap(x) => visitor.visit_item_map(path, x),
+        AstItem::ItemOption(x) => visitor.visit_item_option(path, x),
+        AstItem::ItemRange(x) => visitor.visit_item_range(path, x),
+        AstItem::ItemSet(x) => visitor.visit_item_set(path, x),
+        AstItem::ItemStruct(x) => visitor.visit_item_struct(path, x),
+        AstItem::ItemTuple(x) => visitor.visit_item_tuple(path, x),
+        AstItem::ItemTupleStruct(x) => visitor.visit_item_tuple_struct(path, x),
+        AstItem::ItemUnitStruct(x) => visitor.visit_item_unit_struct(path, x),
+        AstItem::ValueBool(x) => visitor.visit_value_bool(path, x),
+        AstItem::ValueBytes(x) => visitor.visit_value_bytes(path, x),
+        AstItem::ValueDuration(x) => visitor.visit_value_duration(path, x),
+        AstItem::ValueIpAddr(x) => visitor.visit_value_ip_addr(path, x),
+        AstItem::ValueNum(x) => visitor.visit_value_num(path, x),
+        AstItem::ValueOpaque(x) => visitor.visit_value_opaque(path, x),
+        AstItem::ValueSocketAddr(x) => visitor.visit_value_socket_addr(path, x),
+        AstItem::ValueStr(x) => visitor.visit_value_str(path, x),
+    }
+}
+
+pub fn walk_item_list<'a, 'b, V>(visitor: &mut V, path: &mut DiffPath<'a, 'b>, x: &'b ItemList<'a>)
+where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    walk_items(visitor, path, &x.0)
+}
+
+pub fn walk_item_map<'a, 'b, V>(visitor: &mut V, path: &mut DiffPath<'a, 'b>, x: &'b ItemMap<'a>)
+where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    for (k, v) in &x.0 {
+        path.push(DiffPathSegment::Key(Cow::Borrowed(k)));
+        visitor.visit_item(path, v);
+        path.pop();
+    }
+}
+
+pub fn walk_item_option<'a, 'b, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'b>,
+    x: &'b ItemOption<'a>,
+) where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    if let Some(x) = x.0.as_deref() {
+        visitor.visit_item(path, x);
+    }
+}
+
+pub fn walk_item_range<'a, 'b, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'b>,
+    x: &'b ItemRange<'a>,
+) where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    for (name, bound) in [("start", &x.0), ("end", &x.2)] {
+        if let Some(bound) = bound.as_deref() {
+            path.push(DiffPathSegment::Field(name.into()));
+            visitor.visit_item(path, bound);
+            path.pop();
+        }
+    }
+}
+
+pub fn walk_item_set<'a, 'b, V>(visitor: &mut V, path: &mut DiffPath<'a, 'b>, x: &'b ItemSet<'a>)
+where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    walk_items(visitor, path, &x.0)
+}
+
+pub fn walk_item_struct<'a, 'b, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'b>,
+    x: &'b ItemStruct<'a>,
+) where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    for (k, v) in &x.1 {
+        path.push(DiffPathSegment::Field(Cow::Borrowed(k)));
+        visitor.visit_item(path, v);
+        path.pop();
+    }
+}
+
+pub fn walk_item_tuple<'a, 'b, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'b>,
+    x: &'b ItemTuple<'a>,
+) where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    walk_items(visitor, path, &x.0)
+}
+
+pub fn walk_item_tuple_struct<'a, 'b, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'b>,
+    x: &'b ItemTupleStruct<'a>,
+) where
+    V: Visit<'a, 'b> + ?Sized,
+{
+    walk_items(visitor, path, &x.1)
+}
+
+fn walk_items<'a, 'b, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'b>,
+    items: impl IntoIterator<Item = &'b AstItem<'a>>,
+) where
+    'a: 'b,
+    V: Visit<'a, 'b> + ?Sized,
+{
+    for (idx, item) in items.into_iter().enumerate() {
+        path.push(DiffPathSegment::Index(idx, idx));
+        visitor.visit_item(path, item);
+        path.pop();
+    }
+}
+
+/// Call the method of the visitor for the type of the value.
+pub fn walk_item_mut<'a, V>(visitor: &mut V, path: &mut DiffPath<'a, 'a>, item: &mut AstItem<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    match item {
+        AstItem::ItemEmpty(x) => visitor.visit_item_empty_mut(path, x),
+        AstItem::ItemList(x) => visitor.visit_item_list_mut(path, x),
+        AstItem::ItemMap(x) => visitor.visit_item_map_mut(path, x),
+        AstItem::ItemOption(x) => visitor.visit_item_option_mut(path, x),
+        AstItem::ItemRange(x) => visitor.visit_item_range_mut(path, x),
+        AstItem::ItemSet(x) => visitor.visit_item_set_mut(path, x),
+        AstItem::ItemStruct(x) => visitor.visit_item_struct_mut(path, x),
+        AstItem::ItemTuple(x) => visitor.visit_item_tuple_mut(path, x),
+        AstItem::ItemTupleStruct(x) => visitor.visit_item_tuple_struct_mut(path, x),
+        AstItem::ItemUnitStruct(x) => visitor.visit_item_unit_struct_mut(path, x),
+        AstItem::ValueBool(x) => visitor.visit_value_bool_mut(path, x),
+        AstItem::ValueBytes(x) => visitor.visit_value_bytes_mut(path, x),
+        AstItem::ValueDuration(x) => visitor.visit_value_duration_mut(path, x),
+        AstItem::ValueIpAddr(x) => visitor.visit_value_ip_addr_mut(path, x),
+        AstItem::ValueNum(x) => visitor.visit_value_num_mut(path, x),
+        AstItem::ValueOpaque(x) => visitor.visit_value_opaque_mut(path, x),
+        AstItem::ValueSocketAddr(x) => visitor.visit_value_socket_addr_mut(path, x),
+        AstItem::ValueStr(x) => visitor.visit_value_str_mut(path, x),
+    }
+}
+
+pub fn walk_item_list_mut<'a, V>(visitor: &mut V, path: &mut DiffPath<'a, 'a>, x: &mut ItemList<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    walk_items_mut(visitor, path, &mut x.0)
+}
+
+pub fn walk_item_map_mut<'a, V>(visitor: &mut V, path: &mut DiffPath<'a, 'a>, x: &mut ItemMap<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    for (k, v) in &mut x.0 {
+        path.push(DiffPathSegment::Key(Cow::Owned(k.clone())));
+        visitor.visit_item_mut(path, v);
+        path.pop();
+    }
+}
+
+pub fn walk_item_option_mut<'a, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'a>,
+    x: &mut ItemOption<'a>,
+) where
+    V: VisitMut<'a> + ?Sized,
+{
+    if let Some(x) = x.0.as_deref_mut() {
+        visitor.visit_item_mut(path, x);
+    }
+}
+
+pub fn walk_item_range_mut<'a, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'a>,
+    x: &mut ItemRange<'a>,
+) where
+    V: VisitMut<'a> + ?Sized,
+{
+    for (name, bound) in [("start", &mut x.0), ("end", &mut x.2)] {
+        if let Some(bound) = bound.as_deref_mut() {
+            path.push(DiffPathSegment::Field(name.into()));
+            visitor.visit_item_mut(path, bound);
+            path.pop();
+        }
+    }
+}
+
+pub fn walk_item_set_mut<'a, V>(visitor: &mut V, path: &mut DiffPath<'a, 'a>, x: &mut ItemSet<'a>)
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    // Items can't be changed while in a set, since that may change their order (or make them
+    // equal, in which case they're merged).
+    let mut items = mem::take(&mut x.0).into_iter().collect::<Vec<_>>();
+    walk_items_mut(visitor, path, &mut items);
+    x.0 = items.into_iter().collect();
+}
+
+pub fn walk_item_struct_mut<'a, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'a>,
+    x: &mut ItemStruct<'a>,
+) where
+    V: VisitMut<'a> + ?Sized,
+{
+    for (k, v) in &mut x.1 {
+        path.push(DiffPathSegment::Field(k.clone()));
+        visitor.visit_item_mut(path, v);
+        path.pop();
+    }
+}
+
+pub fn walk_item_tuple_mut<'a, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'a>,
+    x: &mut ItemTuple<'a>,
+) where
+    V: VisitMut<'a> + ?Sized,
+{
+    walk_items_mut(visitor, path, &mut x.0)
+}
+
+pub fn walk_item_tuple_struct_mut<'a, V>(
+    visitor: &mut V,
+    path: &mut DiffPath<'a, 'a>,
+    x: &mut ItemTupleStruct<'a>,
+) where
+    V: VisitMut<'a> + ?Sized,
+{
+    walk_items_mut(visitor, path, &mut x.1)
+}
+
+fn walk_items_mut<'a, V>(visitor: &mut V, path: &mut DiffPath<'a, 'a>, items: &mut [AstItem<'a>])
+where
+    V: VisitMut<'a> + ?Sized,
+{
+    for (idx, item) in items.iter_mut().enumerate() {
+        path.push(DiffPathSegment::Index(idx, idx));
+        visitor.visit_item_mut(path, item);
+        path.pop();
+    }
+}
+
+/// Call the method of the folder for the type of the value.
+pub fn walk_item_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    item: AstItem<'a>,
+) -> AstItem<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    match item {
+        AstItem::ItemEmpty(x) => folder.fold_item_empty(path, x).into(),
+        AstItem::ItemList(x) => folder.fold_item_list(path, x).into(),
+        AstItem::ItemMap(x) => folder.fold_item_map(path, x).into(),
+        AstItem::ItemOption(x) => folder.fold_item_option(path, x).into(),
+        AstItem::ItemRange(x) => folder.fold_item_range(path, x).into(),
+        AstItem::ItemSet(x) => folder.fold_item_set(path, x).into(),
+        AstItem::ItemStruct(x) => folder.fold_item_struct(path, x).into(),
+        AstItem::ItemTuple(x) => folder.fold_item_tuple(path, x).into(),
+        AstItem::ItemTupleStruct(x) => folder.fold_item_tuple_struct(path, x).into(),
+        AstItem::ItemUnitStruct(x) => folder.fold_item_unit_struct(path, x).into(),
+        AstItem::ValueBool(x) => folder.fold_value_bool(path, x).into(),
+        AstItem::ValueBytes(x) => folder.fold_value_bytes(path, x).into(),
+        AstItem::ValueDuration(x) => folder.fold_value_duration(path, x).into(),
+        AstItem::ValueIpAddr(x) => folder.fold_value_ip_addr(path, x).into(),
+        AstItem::ValueNum(x) => folder.fold_value_num(path, x).into(),
+        AstItem::ValueOpaque(x) => folder.fold_value_opaque(path, x).into(),
+        AstItem::ValueSocketAddr(x) => folder.fold_value_socket_addr(path, x).into(),
+        AstItem::ValueStr(x) => folder.fold_value_str(path, x).into(),
+    }
+}
+
+pub fn walk_item_list_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemList<'a>,
+) -> ItemList<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    ItemList(walk_items_fold(folder, path, x.0).collect())
+}
+
+pub fn walk_item_map_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemMap<'a>,
+) -> ItemMap<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    let entries = x.0.into_iter().map(|(k, v)| {
+        path.push(DiffPathSegment::Key(Cow::Owned(k.clone())));
+        let v = folder.fold_item(path, v);
+        path.pop();
+        (k, v)
+    });
+    ItemMap(entries.collect())
+}
+
+pub fn walk_item_option_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemOption<'a>,
+) -> ItemOption<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    ItemOption(x.0.map(|x| Box::new(folder.fold_item(path, *x))))
+}
+
+pub fn walk_item_range_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemRange<'a>,
+) -> ItemRange<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    let mut fold_bound = |name: &'static str, bound: Option<Box<AstItem<'a>>>| {
+        bound.map(|bound| {
+            path.push(DiffPathSegment::Field(name.into()));
+            let bound = folder.fold_item(path, *bound);
+            path.pop();
+            Box::new(bound)
+        })
+    };
+    let start = fold_bound("start", x.0);
+    let end = fold_bound("end", x.2);
+    ItemRange(start, x.1, end)
+}
+
+pub fn walk_item_set_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemSet<'a>,
+) -> ItemSet<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    // Items which became equal are merged.
+    ItemSet(walk_items_fold(folder, path, x.0).collect())
+}
+
+pub fn walk_item_struct_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemStruct<'a>,
+) -> ItemStruct<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    let fields = x.1.into_iter().map(|(k, v)| {
+        path.push(DiffPathSegment::Field(k.clone()));
+        let v = folder.fold_item(path, v);
+        path.pop();
+        (k, v)
+    });
+    ItemStruct(x.0, fields.collect(), x.2)
+}
+
+pub fn walk_item_tuple_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemTuple<'a>,
+) -> ItemTuple<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    ItemTuple(walk_items_fold(folder, path, x.0).collect())
+}
+
+pub fn walk_item_tuple_struct_fold<'a, F>(
+    folder: &mut F,
+    path: &mut DiffPath<'a, 'a>,
+    x: ItemTupleStruct<'a>,
+) -> ItemTupleStruct<'a>
+where
+    F: Fold<'a> + ?Sized,
+{
+    ItemTupleStruct(x.0, walk_items_fold(folder, path, x.1).collect())
+}
+
+fn walk_items_fold<'a, 'f, F>(
+    folder: &'f mut F,
+    path: &'f mut DiffPath<'a, 'a>,
+    items: impl IntoIterator<Item = AstItem<'a>> + 'f,
+) -> impl Iterator<Item = AstItem<'a>> + 'f
+where
+    F: Fold<'a> + ?Sized,
+{
+    items.into_iter().enumerate().map(|(idx, item)| {
+        path.push(DiffPathSegment::Index(idx, idx));
+        let item = folder.fold_item(path, item);
+        path.pop();
+        item
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, render::PathText};
+
+    const TREE: &str =
+        r#"Foo { a: [1, (2, Some(3))], b: {"k": 4..=5}, c: {6, 7}, d: Bar(..8, None) }"#;
+    const PATHS: [&str; 8] = [
+        ".a[0] 1",
+        ".a[1][0] 2",
+        ".a[1][1] 3",
+        r#".b["k"].start 4"#,
+        r#".b["k"].end 5"#,
+        ".c[0] 6",
+        ".c[1] 7",
+        ".d[0].end 8",
+    ];
+
+    /// Records the path of each number and adds one to it.
+    #[derive(Default)]
+    struct Numbers(Vec<String>);
+
+    impl Numbers {
+        fn record(&mut self, path: &DiffPath, x: &ValueNum) {
+            self.0.push(format!("{} {x}", PathText(path)));
+        }
+    }
+
+    fn increment(x: &ValueNum) -> ValueNum<'static> {
+        ValueNum((x.0.parse::<u32>().unwrap() + 1).to_string().into())
+    }
+
+    impl<'a, 'b> Visit<'a, 'b> for Numbers {
+        fn visit_value_num(&mut self, path: &mut DiffPath<'a, 'b>, x: &'b ValueNum<'a>) {
+            self.record(path, x);
+        }
+    }
+
+    impl<'a> VisitMut<'a> for Numbers {
+        fn visit_value_num_mut(&mut self, path: &mut DiffPath<'a, 'a>, x: &mut ValueNum<'a>) {
+            self.record(path, x);
+            *x = increment(x);
+        }
+    }
+
+    impl<'a> Fold<'a> for Numbers {
+        fn fold_value_num(&mut self, path: &mut DiffPath<'a, 'a>, x: ValueNum<'a>) -> ValueNum<'a> {
+            self.record(path, &x);
+            increment(&x)
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let tree = parse(TREE).unwrap();
+        let expected =
+            parse(r#"Foo { a: [2, (3, Some(4))], b: {"k": 5..=6}, c: {7, 8}, d: Bar(..9, None) }"#)
+                .unwrap();
+
+        let mut numbers = Numbers::default();
+        numbers.visit_item(&mut Vec::new(), &tree);
+        assert_eq!(numbers.0, PATHS);
+
+        let mut numbers = Numbers::default();
+        let mut changed = tree.clone();
+        numbers.visit_item_mut(&mut Vec::new(), &mut changed);
+        assert_eq!(numbers.0, PATHS);
+        assert_eq!(changed, expected);
+
+        let mut numbers = Numbers::default();
+        let folded = numbers.fold_item(&mut Vec::new(), tree);
+        assert_eq!(numbers.0, PATHS);
+        assert_eq!(folded, expected);
+    }
+
+    /// Replaces every number with zero.
+    struct Zero;
+
+    impl<'a> VisitMut<'a> for Zero {
+        fn visit_value_num_mut(&mut self, _path: &mut DiffPath<'a, 'a>, x: &mut ValueNum<'a>) {
+            *x = ValueNum("0".into());
+        }
+    }
+
+    impl<'a> Fold<'a> for Zero {
+        fn fold_value_num(
+            &mut self,
+            _path: &mut DiffPath<'a, 'a>,
+            _x: ValueNum<'a>,
+        ) -> ValueNum<'a> {
+            ValueNum("0".into())
+        }
+    }
+
+    #[test]
+    fn sets_merge() {
+        let tree = parse("[{1, 2}, {(1, a), (2, b)}]").unwrap();
+        let expected = parse("[{0}, {(0, a), (0, b)}]").unwrap();
+
+        let mut changed = tree.clone();
+        Zero.visit_item_mut(&mut Vec::new(), &mut changed);
+        assert_eq!(changed, expected);
+        assert_eq!(Zero.fold_item(&mut Vec::new(), tree), expected);
+    }
+}